@@ -4,17 +4,268 @@ use ink_lang as ink;
 
 #[ink::contract]
 mod documents {
+    use ink_env::hash::Blake2x256;
+    use ink_prelude::string::String;
     use ink_prelude::vec::Vec;
     use ink_storage::collections::HashMap;
     use ink_storage::traits::{PackedLayout, SpreadLayout};
 
+    /// Domain separator of the pre-signed document hash
+    const PRE_SIGNED_DOCUMENT_DOMAIN: u8 = 0;
+    /// Domain separator of the pre-signed opened at hash
+    const PRE_SIGNED_OPENED_AT_DOMAIN: u8 = 1;
+    /// Maximum number of documents returned in a page
+    const MAX_PAGE_SIZE: u32 = 50;
+    /// Maximum number of documents scanned for a page
+    const MAX_PAGE_SCAN: u32 = 200;
+    /// Maximum number of receivers of a document sent to many receivers
+    const MAX_RECEIVERS: usize = 50;
+    /// Maximum length of a symmetric key wrapped to the encryption key of a grantee
+    const MAX_WRAPPED_KEY_LENGTH: usize = 128;
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum HashAlgorithm {
+        Sha256,
+        Sha512,
+        Keccak256,
+        Blake2b256,
+    }
+
+    impl HashAlgorithm {
+        fn digest_size(&self) -> usize {
+            match self {
+                HashAlgorithm::Sha512 => 64,
+                _ => 32,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum EncryptionScheme {
+        None,
+        X25519XSalsa20Poly1305,
+        X25519Aes256Gcm,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct EncryptionKey {
+        scheme: EncryptionScheme,
+        /// X25519 public key senders encrypt the documents of the receiver to
+        public_key: [u8; 32],
+        /// Incremented on every rotation, starting at one
+        version: u32,
+    }
+
+    #[derive(Clone, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct DocumentPayload {
+        version: u8,
+        uri: String,
+        hash_algorithm: HashAlgorithm,
+        digest: Vec<u8>,
+        mime_type: String,
+        size: u64,
+        encryption_scheme: EncryptionScheme,
+        /// Timestamp from which the document can no longer be opened, none if it never expires
+        expires_at: Option<Timestamp>,
+    }
+
+    impl DocumentPayload {
+        const VERSION: u8 = 2;
+        const MAX_URI_LENGTH: usize = 256;
+        const MAX_MIME_TYPE_LENGTH: usize = 64;
+
+        fn is_valid(&self) -> bool {
+            self.version == Self::VERSION
+                && !self.uri.is_empty()
+                && self.uri.len() <= Self::MAX_URI_LENGTH
+                && self.digest.len() == self.hash_algorithm.digest_size()
+                && !self.mime_type.is_empty()
+                && self.mime_type.len() <= Self::MAX_MIME_TYPE_LENGTH
+        }
+    }
+
+    #[derive(Clone, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum DocumentData {
+        /// The payload stored with the document.
+        Payload(DocumentPayload),
+        /// Reference to the payload shared by the receivers of a document sent to many receivers.
+        Content {
+            id: u32,
+            expires_at: Option<Timestamp>,
+        },
+    }
+
+    impl DocumentData {
+        fn expires_at(&self) -> Option<Timestamp> {
+            match self {
+                DocumentData::Payload(payload) => payload.expires_at,
+                DocumentData::Content { expires_at, .. } => *expires_at,
+            }
+        }
+    }
+
     #[derive(Clone, Debug, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct Document {
         sender: AccountId,
-        data: Vec<u8>,
+        data: DocumentData,
         sent_at: Timestamp,
         opened_at: Timestamp,
+        revoked_at: Timestamp,
+        /// Receipt of the receiver acknowledging or rejecting the document
+        receipt: Option<Receipt>,
+        /// Index of the document of the sender this document replies to
+        in_reply_to: Option<u32>,
+        /// Global ID of the document, the hash of its sender, receiver, index and data
+        id: Hash,
+        /// Version of the receiver encryption key when the document was added, zero if the
+        /// receiver did not publish a key
+        encryption_key_version: u32,
+        /// Original document the document was forwarded from, the sender is the forwarder
+        forwarded_from: Option<ForwardedFrom>,
+        /// Inbox state the receiver organizes the document by
+        receiver_metadata: ReceiverMetadata,
+    }
+
+    #[derive(Clone, Copy, Debug, Default, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ReceiverMetadata {
+        archived: bool,
+        pinned: bool,
+        /// Bitset of the labels of the receiver, every bit is a label or folder defined by the clients
+        labels: u64,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ForwardedFrom {
+        /// The receiver of the original document, the forwarder
+        receiver: AccountId,
+        index: u32,
+        /// The sender of the original document
+        sender: AccountId,
+        sent_at: Timestamp,
+    }
+
+    #[derive(Clone, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Receipt {
+        status: ReceiptStatus,
+        /// Hash of the off-chain reason given by the receiver
+        reason_hash: Option<Hash>,
+        issued_at: Timestamp,
+    }
+
+    #[derive(Clone, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct KeyEnvelope {
+        /// The receiver or the sender of the document that granted the access
+        granted_by: AccountId,
+        /// Symmetric key of the document wrapped to the encryption key of the grantee
+        wrapped_key: Vec<u8>,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ReceiptStatus {
+        Acknowledged,
+        Rejected,
+    }
+
+    impl Document {
+        fn status(&self, now: Timestamp) -> DocumentStatus {
+            if self.opened_at != Timestamp::default() {
+                DocumentStatus::Opened
+            } else if self.revoked_at != Timestamp::default() {
+                DocumentStatus::Revoked
+            } else if matches!(self.data.expires_at(), Some(expires_at) if now >= expires_at) {
+                DocumentStatus::Expired
+            } else {
+                DocumentStatus::Unopened
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum DocumentStatus {
+        /// The receiver can open the document.
+        Unopened,
+        /// The receiver opened the document.
+        Opened,
+        /// The sender recalled the document before it was opened.
+        Revoked,
+        /// The document expired before it was opened.
+        Expired,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum AccessPolicy {
+        /// Every sender can add documents.
+        Open,
+        /// Only allowed senders can add documents.
+        Allowlist,
+        /// Every sender except the blocked ones can add documents.
+        Blocklist,
+    }
+
+    impl AccessPolicy {
+        fn allows(&self, sender_access: Option<SenderAccess>) -> bool {
+            match self {
+                AccessPolicy::Open => true,
+                AccessPolicy::Allowlist => sender_access == Some(SenderAccess::Allowed),
+                AccessPolicy::Blocklist => sender_access != Some(SenderAccess::Blocked),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SenderAccess {
+        Allowed,
+        Blocked,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Order {
+        /// From the oldest to the newest document.
+        Ascending,
+        /// From the newest to the oldest document.
+        Descending,
+    }
+
+    #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum DocumentsFilter {
+        All,
+        Opened,
+        Unopened,
+    }
+
+    impl DocumentsFilter {
+        fn matches(&self, document: &Document, now: Timestamp) -> bool {
+            match self {
+                DocumentsFilter::All => true,
+                DocumentsFilter::Opened => document.status(now) == DocumentStatus::Opened,
+                DocumentsFilter::Unopened => document.status(now) == DocumentStatus::Unopened,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct DocumentsPage {
+        /// Documents of the page together with their indexes.
+        documents: Vec<(u32, Document)>,
+        /// Index to request the next page from, none if there are no more documents.
+        next: Option<u32>,
     }
 
     #[derive(Clone, Copy, Debug, scale::Encode, scale::Decode, Eq, PartialEq)]
@@ -26,11 +277,67 @@ mod documents {
         NotExist,
         /// The document was already opened.
         AlreadyOpened,
+        /// The signature does not belong to the signer.
+        InvalidSignature,
+        /// A transaction with the same parameters was already executed.
+        AlreadyExecuted,
+        /// The document payload is not well-formed.
+        InvalidPayload,
+        /// The access policy of the receiver does not allow the sender.
+        SenderNotAllowed,
+        /// The delegate does not exist.
+        DelegateNotExist,
+        /// The attached balance is lower than the postage of the receiver.
+        InsufficientPostage,
+        /// The postage could not be transferred to the receiver.
+        TransferFailed,
+        /// The sender recalled the document.
+        Revoked,
+        /// The document expired.
+        Expired,
+        /// The list of receivers is empty or too long.
+        InvalidReceivers,
+        /// The receiver already acknowledged or rejected the document.
+        ReceiptAlreadyIssued,
+        /// The encryption key has no encryption scheme.
+        InvalidEncryptionKey,
+        /// The wrapped key is empty or too long.
+        InvalidWrappedKey,
+        /// The grantee already has access to the document.
+        KeyEnvelopeAlreadyExists,
+        /// The grantee has no access to the document.
+        KeyEnvelopeNotExist,
+        /// The encryption key version does not match the current key of the receiver.
+        EncryptionKeyVersionMismatch,
     }
 
     #[ink(storage)]
     pub struct Documents {
-        documents: HashMap<AccountId, Vec<Document>>,
+        /// Every document is stored in its own storage cell, keyed by receiver and index
+        documents: HashMap<(AccountId, u32), Document>,
+        documents_count: HashMap<AccountId, u32>,
+        /// References of the sender's documents, keyed by sender and sent index
+        sent_documents: HashMap<(AccountId, u32), (AccountId, u32)>,
+        sent_documents_count: HashMap<AccountId, u32>,
+        executed_settlements: HashMap<(AccountId, Hash), bool>,
+        /// Access policies of the receivers, receivers without one are open
+        access_policies: HashMap<AccountId, AccessPolicy>,
+        /// Allowed and blocked senders, keyed by receiver and sender
+        senders_access: HashMap<(AccountId, AccountId), SenderAccess>,
+        /// Delegates opening documents on behalf of the receiver, keyed by receiver and
+        /// delegate, with the timestamp from which the delegate can no longer act
+        delegates: HashMap<(AccountId, AccountId), Option<Timestamp>>,
+        /// Balance the sender attaches to every document of the receiver
+        postages: HashMap<AccountId, Balance>,
+        /// Payloads shared by the receivers of documents sent to many receivers
+        contents: HashMap<u32, DocumentPayload>,
+        contents_count: u32,
+        /// Receiver and index of the documents, keyed by global document ID
+        document_ids: HashMap<Hash, (AccountId, u32)>,
+        /// Encryption keys published by the receivers
+        encryption_keys: HashMap<AccountId, EncryptionKey>,
+        /// Parties granted access to the documents, keyed by global document ID and grantee
+        key_envelopes: HashMap<(Hash, AccountId), KeyEnvelope>,
     }
 
     #[ink(event)]
@@ -39,166 +346,2037 @@ mod documents {
         sender: AccountId,
         #[ink(topic)]
         receiver: AccountId,
-        data: Vec<u8>,
+        data: DocumentData,
         sent_at: Timestamp,
     }
 
+    #[ink(event)]
+    pub struct AddContent {
+        #[ink(topic)]
+        sender: AccountId,
+        id: u32,
+        data: DocumentPayload,
+    }
+
+    #[ink(event)]
+    pub struct ForwardDocument {
+        #[ink(topic)]
+        forwarder: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        /// Index of the original document of the forwarder
+        index: u32,
+    }
+
     #[ink(event)]
     pub struct SetOpenedAt {
         #[ink(topic)]
         receiver: AccountId,
+        /// The receiver or the delegate that opened the document
+        #[ink(topic)]
+        opened_by: AccountId,
         index: u32,
         opened_at: Timestamp,
     }
 
+    #[ink(event)]
+    pub struct RecallDocument {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        index: u32,
+        revoked_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct IssueReceipt {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        index: u32,
+        receipt: Receipt,
+    }
+
+    #[ink(event)]
+    pub struct SetReceiverMetadata {
+        #[ink(topic)]
+        receiver: AccountId,
+        index: u32,
+        metadata: ReceiverMetadata,
+    }
+
+    #[ink(event)]
+    pub struct SetEncryptionKey {
+        #[ink(topic)]
+        receiver: AccountId,
+        key: EncryptionKey,
+    }
+
+    #[ink(event)]
+    pub struct GrantDocumentAccess {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        grantee: AccountId,
+        index: u32,
+        granted_by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RevokeDocumentAccess {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        grantee: AccountId,
+        index: u32,
+        revoked_by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AddDelegate {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        delegate: AccountId,
+        expires_at: Option<Timestamp>,
+    }
+
+    #[ink(event)]
+    pub struct RemoveDelegate {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        delegate: AccountId,
+    }
+
     impl Documents {
         #[ink(constructor)]
         pub fn new() -> Self {
             Self {
                 documents: HashMap::new(),
+                documents_count: HashMap::new(),
+                sent_documents: HashMap::new(),
+                sent_documents_count: HashMap::new(),
+                executed_settlements: HashMap::new(),
+                access_policies: HashMap::new(),
+                senders_access: HashMap::new(),
+                delegates: HashMap::new(),
+                postages: HashMap::new(),
+                contents: HashMap::new(),
+                contents_count: 0,
+                document_ids: HashMap::new(),
+                encryption_keys: HashMap::new(),
+                key_envelopes: HashMap::new(),
             }
         }
 
-        #[ink(message)]
-        pub fn add_document(&mut self, receiver: AccountId, data: Vec<u8>) -> bool {
-            if !self.documents.contains_key(&receiver) {
-                self.documents.insert(receiver, Vec::new());
-            }
+        /// Add the document to the `receiver`, optionally as a reply to the document `in_reply_to`
+        /// the caller received. The document is encrypted to the `encryption_key_version` of the
+        /// key of the receiver, zero when the receiver has no key. The caller attaches at least
+        /// the postage of the receiver and the whole attached balance is transferred to the
+        /// receiver, or returned to the caller when the document can not be added.
+        #[ink(message, payable)]
+        pub fn add_document(
+            &mut self,
+            receiver: AccountId,
+            data: DocumentPayload,
+            in_reply_to: Option<u32>,
+            encryption_key_version: u32,
+        ) -> Result<(), Error> {
+            self.refund_on_error(|contract| {
+                let sender = contract.env().caller();
 
-            let sender = self.env().caller();
-            let sent_at = self.env().block_timestamp();
+                if !data.is_valid() {
+                    return Err(Error::InvalidPayload);
+                }
 
-            self.documents.get_mut(&receiver).unwrap().push(Document {
-                sender,
-                data: data.clone(),
-                sent_at,
-                opened_at: Timestamp::default(),
-            });
+                let postage = contract.env().transferred_balance();
 
-            self.env().emit_event(AddDocument {
-                sender,
-                receiver,
-                data,
-                sent_at,
-            });
+                contract.store_document(
+                    sender,
+                    receiver,
+                    DocumentData::Payload(data),
+                    in_reply_to,
+                    None,
+                    encryption_key_version,
+                    postage,
+                )
+            })
+        }
+
+        /// Add the document to every receiver, storing the payload once. Every receiver comes
+        /// with the version of its key the document is encrypted to. The caller attaches at
+        /// least the sum of the postages of the receivers, every receiver gets its postage and
+        /// the rest of the attached balance is returned to the caller.
+        #[ink(message, payable)]
+        pub fn add_document_to_many(
+            &mut self,
+            receivers: Vec<(AccountId, u32)>,
+            data: DocumentPayload,
+            in_reply_to: Option<u32>,
+        ) -> Result<u32, Error> {
+            self.refund_on_error(|contract| {
+                let sender = contract.env().caller();
+
+                if !data.is_valid() {
+                    return Err(Error::InvalidPayload);
+                }
+
+                if receivers.is_empty() || receivers.len() > MAX_RECEIVERS {
+                    return Err(Error::InvalidReceivers);
+                }
+
+                let id = contract.contents_count;
+                let document_data = DocumentData::Content {
+                    id,
+                    expires_at: data.expires_at,
+                };
+
+                // Check every receiver before the first write, so no receiver gets the document
+                // when the document can not be added to all of them
+                let mut total_postage: Balance = 0;
+
+                for (receiver, encryption_key_version) in &receivers {
+                    let postage = contract.get_postage(*receiver);
+                    contract.check_document(
+                        sender,
+                        *receiver,
+                        &document_data,
+                        in_reply_to,
+                        *encryption_key_version,
+                        postage,
+                    )?;
+
+                    total_postage = total_postage.checked_add(postage)
+                        .ok_or(Error::InsufficientPostage)?;
+                }
+
+                let remaining = contract.env().transferred_balance().checked_sub(total_postage)
+                    .ok_or(Error::InsufficientPostage)?;
+
+                // Pay every postage and return the rest before the first write. The balance
+                // transferred before a failed transfer can not be refunded, so the call traps
+                // to revert it.
+                for (receiver, _) in &receivers {
+                    let postage = contract.get_postage(*receiver);
+
+                    if postage > 0 && contract.env().transfer(*receiver, postage).is_err() {
+                        panic!("the postage could not be transferred to the receiver");
+                    }
+                }
+
+                if remaining > 0 && contract.env().transfer(sender, remaining).is_err() {
+                    panic!("the remaining balance could not be returned");
+                }
+
+                contract.contents.insert(id, data.clone());
+                contract.contents_count = id + 1;
+
+                contract.env().emit_event(AddContent {
+                    sender,
+                    id,
+                    data,
+                });
+
+                for (receiver, _) in receivers {
+                    contract.insert_document(sender, receiver, document_data.clone(), in_reply_to, None);
+                }
+
+                Ok(id)
+            })
+        }
+
+        /// Forward the document with the `index` the caller received to the `receiver`. The new
+        /// document copies the data of the original and records its receiver, index, sender and
+        /// sent at, the caller is its sender and attaches at least the postage of the receiver.
+        /// The new document is encrypted to the `encryption_key_version` of the receiver.
+        #[ink(message, payable)]
+        pub fn forward_document(
+            &mut self,
+            index: u32,
+            receiver: AccountId,
+            encryption_key_version: u32,
+        ) -> Result<(), Error> {
+            self.refund_on_error(|contract| {
+                let forwarder = contract.env().caller();
+                let now = contract.env().block_timestamp();
+
+                let doc = contract.documents.get(&(forwarder, index))
+                    .ok_or(Error::NotExist)?;
+
+                match doc.status(now) {
+                    DocumentStatus::Unopened | DocumentStatus::Opened => {}
+                    DocumentStatus::Revoked => return Err(Error::Revoked),
+                    DocumentStatus::Expired => return Err(Error::Expired),
+                }
+
+                let data = doc.data.clone();
+                let forwarded_from = ForwardedFrom {
+                    receiver: forwarder,
+                    index,
+                    sender: doc.sender,
+                    sent_at: doc.sent_at,
+                };
+
+                let postage = contract.env().transferred_balance();
+
+                contract.store_document(
+                    forwarder,
+                    receiver,
+                    data,
+                    None,
+                    Some(forwarded_from),
+                    encryption_key_version,
+                    postage,
+                )?;
+
+                contract.env().emit_event(ForwardDocument {
+                    forwarder,
+                    receiver,
+                    index,
+                });
 
-            true
+                Ok(())
+            })
         }
 
+        /// Set opened at of the document, the caller must be the receiver or its active delegate.
         #[ink(message)]
         pub fn set_opened_at(&mut self, receiver: AccountId, index: u32) -> Result<(), Error> {
             let caller = self.env().caller();
-            let sent_at = self.env().block_timestamp();
 
-            if receiver != caller {
+            if receiver != caller && !self.is_active_delegate(receiver, caller) {
+                return Err(Error::NotAllowed);
+            }
+
+            self.open_document(receiver, index, caller)
+        }
+
+        /// Recall the unopened document, so the receiver can no longer open it. Only the
+        /// sender of the document can recall it.
+        #[ink(message)]
+        pub fn recall_document(&mut self, receiver: AccountId, index: u32) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let revoked_at = self.env().block_timestamp();
+
+            let doc = self.documents.get_mut(&(receiver, index))
+                .ok_or(Error::NotExist)?;
+
+            if doc.sender != caller {
                 return Err(Error::NotAllowed);
             }
 
-            if index >= self.get_documents_count(receiver) {
-                return Err(Error::NotExist);
+            if doc.opened_at != Timestamp::default() {
+                return Err(Error::AlreadyOpened);
             }
 
-            let doc = self.documents.get_mut(&receiver)
-                .ok_or(Error::NotExist)
-                .unwrap()
-                .get_mut(index as usize)
-                .ok_or(Error::NotExist)
-                .unwrap();
+            if doc.revoked_at != Timestamp::default() {
+                return Err(Error::Revoked);
+            }
 
-            doc.opened_at = sent_at;
+            doc.revoked_at = revoked_at;
 
-            self.env().emit_event(SetOpenedAt {
+            self.env().emit_event(RecallDocument {
+                sender: caller,
                 receiver,
                 index,
-                opened_at: sent_at,
+                revoked_at,
             });
 
             Ok(())
         }
 
+        /// Acknowledge the document of the caller, opening it if it is not opened yet.
         #[ink(message)]
-        pub fn get_documents(&self, receiver: AccountId) -> Option<Vec<Document>> {
-            let docs = self.documents.get(&receiver)?;
-            Some(docs.clone())
+        pub fn acknowledge_document(&mut self, index: u32, reason_hash: Option<Hash>) -> Result<(), Error> {
+            let receiver = self.env().caller();
+            self.issue_receipt(receiver, index, ReceiptStatus::Acknowledged, reason_hash)
         }
 
+        /// Reject the document of the caller, opening it if it is not opened yet.
         #[ink(message)]
-        pub fn get_document(&self, receiver: AccountId, index: u32) -> Option<Document> {
-            let doc = self.documents.get(&receiver)?.get(index as usize)?;
-            Some(doc.clone())
+        pub fn reject_document(&mut self, index: u32, reason_hash: Option<Hash>) -> Result<(), Error> {
+            let receiver = self.env().caller();
+            self.issue_receipt(receiver, index, ReceiptStatus::Rejected, reason_hash)
         }
 
+        /// Set the archived flag, pinned flag and labels the caller organizes its document by.
         #[ink(message)]
-        pub fn get_documents_count(&self, receiver: AccountId) -> u32 {
-            self.documents.get(&receiver).unwrap_or(&Vec::new()).len() as u32
-        }
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use ink_lang as ink;
+        pub fn set_receiver_metadata(&mut self, index: u32, metadata: ReceiverMetadata) -> Result<(), Error> {
+            let receiver = self.env().caller();
 
-        use super::*;
+            let doc = self.documents.get_mut(&(receiver, index))
+                .ok_or(Error::NotExist)?;
 
-        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
-            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
-                .expect("off-chain environment should have been initialized already")
-        }
+            doc.receiver_metadata = metadata;
 
-        fn add_document(contract: &mut Documents, receiver: AccountId) -> bool {
-            contract.add_document(
+            self.env().emit_event(SetReceiverMetadata {
                 receiver,
-                String::from("0x18747470733a2f2f656d6e3137382e6769746875622e696f2f6f6e6c696e652d746f6f6c732f7368613235362e68746d6ce2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d197467165c9").into_bytes(),
-            )
+                index,
+                metadata,
+            });
+
+            Ok(())
         }
 
-        #[ink::test]
-        fn adding_documents_works() {
-            let mut contract = Documents::new();
-            let default_accounts = default_accounts();
+        /// Grant the `grantee` access to the document with the symmetric key of the document
+        /// wrapped to the encryption key of the grantee. The caller must be the receiver or
+        /// the sender of the document.
+        #[ink(message)]
+        pub fn grant_document_access(
+            &mut self,
+            receiver: AccountId,
+            index: u32,
+            grantee: AccountId,
+            wrapped_key: Vec<u8>,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
 
-            assert_eq!(contract.get_documents_count(default_accounts.alice), 0);
-            assert_eq!(add_document(&mut contract, default_accounts.alice), true);
-            assert_eq!(ink_env::test::recorded_events().count(), 1);
-            assert_eq!(add_document(&mut contract, default_accounts.alice), true);
-            assert_eq!(add_document(&mut contract, default_accounts.bob), true);
-            assert_eq!(contract.get_documents_count(default_accounts.alice), 2);
-            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
-        }
+            if wrapped_key.is_empty() || wrapped_key.len() > MAX_WRAPPED_KEY_LENGTH {
+                return Err(Error::InvalidWrappedKey);
+            }
 
-        #[ink::test]
-        fn retrieving_documents_works() {
-            let mut contract = Documents::new();
-            let default_accounts = default_accounts();
+            let id = self.get_document_id_of_party(receiver, index, caller)?;
 
-            assert_eq!(contract.get_documents(default_accounts.alice).is_none(), true);
-            add_document(&mut contract, default_accounts.alice);
-            add_document(&mut contract, default_accounts.alice);
-            assert_eq!(contract.get_documents(default_accounts.alice).is_some(), true);
-            assert_eq!(contract.get_documents(default_accounts.alice).unwrap().len(), 2);
-        }
+            if self.key_envelopes.contains_key(&(id, grantee)) {
+                return Err(Error::KeyEnvelopeAlreadyExists);
+            }
 
-        #[ink::test]
-        fn retrieving_document_works() {
-            let mut contract = Documents::new();
-            let default_accounts = default_accounts();
+            self.key_envelopes.insert((id, grantee), KeyEnvelope {
+                granted_by: caller,
+                wrapped_key,
+            });
 
-            assert_eq!(contract.get_document(default_accounts.alice, 0).is_none(), true);
-            assert_eq!(contract.get_document(default_accounts.alice, 1).is_none(), true);
-            add_document(&mut contract, default_accounts.alice);
-            add_document(&mut contract, default_accounts.alice);
-            assert_eq!(contract.get_document(default_accounts.alice, 0).is_some(), true);
+            self.env().emit_event(GrantDocumentAccess {
+                receiver,
+                grantee,
+                index,
+                granted_by: caller,
+            });
+
+            Ok(())
         }
 
-        #[ink::test]
-        fn setting_opened_at_works() {
-            let mut contract = Documents::new();
-            let default_accounts = default_accounts();
+        /// Revoke the access of the `grantee` to the document. The caller must be the
+        /// receiver or the sender of the document.
+        #[ink(message)]
+        pub fn revoke_document_access(&mut self, receiver: AccountId, index: u32, grantee: AccountId) -> Result<(), Error> {
+            let caller = self.env().caller();
 
-            assert_eq!(contract.set_opened_at(default_accounts.bob, 0), Err(Error::NotAllowed));
-            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Err(Error::NotExist));
-            add_document(&mut contract, default_accounts.alice);
-            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Ok(()));
+            let id = self.get_document_id_of_party(receiver, index, caller)?;
+
+            self.key_envelopes.take(&(id, grantee))
+                .ok_or(Error::KeyEnvelopeNotExist)?;
+
+            self.env().emit_event(RevokeDocumentAccess {
+                receiver,
+                grantee,
+                index,
+                revoked_by: caller,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_key_envelope(&self, receiver: AccountId, index: u32, grantee: AccountId) -> Option<KeyEnvelope> {
+            let doc = self.documents.get(&(receiver, index))?;
+            self.key_envelopes.get(&(doc.id, grantee)).cloned()
+        }
+
+        /// Authorize the `delegate` to open documents of the caller until `expires_at`,
+        /// or without expiry when it is none. Adding an existing delegate updates its expiry.
+        #[ink(message)]
+        pub fn add_delegate(&mut self, delegate: AccountId, expires_at: Option<Timestamp>) {
+            let receiver = self.env().caller();
+
+            self.delegates.insert((receiver, delegate), expires_at);
+
+            self.env().emit_event(AddDelegate {
+                receiver,
+                delegate,
+                expires_at,
+            });
+        }
+
+        #[ink(message)]
+        pub fn remove_delegate(&mut self, delegate: AccountId) -> Result<(), Error> {
+            let receiver = self.env().caller();
+
+            self.delegates.take(&(receiver, delegate))
+                .ok_or(Error::DelegateNotExist)?;
+
+            self.env().emit_event(RemoveDelegate {
+                receiver,
+                delegate,
+            });
+
+            Ok(())
+        }
+
+        /// Return the expiry of the `delegate`, none if it is not a delegate of the `receiver`.
+        #[ink(message)]
+        pub fn get_delegate(&self, receiver: AccountId, delegate: AccountId) -> Option<Option<Timestamp>> {
+            self.delegates.get(&(receiver, delegate)).copied()
+        }
+
+        /// Add the document on behalf of the `sender`, who signed the hash returned by
+        /// `calculate_pre_signed_document_hash` with their ecdsa key. The caller attaches
+        /// the postage of the receiver.
+        #[ink(message, payable)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn add_pre_signed_document(
+            &mut self,
+            sender: AccountId,
+            receiver: AccountId,
+            data: DocumentPayload,
+            in_reply_to: Option<u32>,
+            encryption_key_version: u32,
+            nonce: u64,
+            signature: Vec<u8>,
+        ) -> Result<(), Error> {
+            self.refund_on_error(|contract| {
+                if !data.is_valid() {
+                    return Err(Error::InvalidPayload);
+                }
+
+                let hash = contract.calculate_pre_signed_document_hash(
+                    sender,
+                    receiver,
+                    data.clone(),
+                    in_reply_to,
+                    encryption_key_version,
+                    nonce,
+                );
+
+                if !contract.is_valid_signature(sender, hash, &signature) {
+                    return Err(Error::InvalidSignature);
+                }
+
+                if contract.is_executed_settlement(sender, hash) {
+                    return Err(Error::AlreadyExecuted);
+                }
+
+                let postage = contract.env().transferred_balance();
+
+                contract.store_document(
+                    sender,
+                    receiver,
+                    DocumentData::Payload(data),
+                    in_reply_to,
+                    None,
+                    encryption_key_version,
+                    postage,
+                )?;
+                contract.executed_settlements.insert((sender, hash), true);
+
+                Ok(())
+            })
+        }
+
+        /// Set opened at on behalf of the `receiver`, who signed the hash returned by
+        /// `calculate_pre_signed_opened_at_hash` with their ecdsa key.
+        #[ink(message)]
+        pub fn set_pre_signed_opened_at(
+            &mut self,
+            receiver: AccountId,
+            index: u32,
+            nonce: u64,
+            signature: Vec<u8>,
+        ) -> Result<(), Error> {
+            let hash = self.calculate_pre_signed_opened_at_hash(receiver, index, nonce);
+
+            if !self.is_valid_signature(receiver, hash, &signature) {
+                return Err(Error::InvalidSignature);
+            }
+
+            if self.is_executed_settlement(receiver, hash) {
+                return Err(Error::AlreadyExecuted);
+            }
+
+            self.open_document(receiver, index, receiver)?;
+            self.executed_settlements.insert((receiver, hash), true);
+
+            Ok(())
+        }
+
+        /// Set the policy deciding which senders can add documents to the caller.
+        #[ink(message)]
+        pub fn set_access_policy(&mut self, policy: AccessPolicy) {
+            let receiver = self.env().caller();
+            self.access_policies.insert(receiver, policy);
+        }
+
+        /// Allow or block the `sender`, or remove it from the lists of the caller when
+        /// `access` is none.
+        #[ink(message)]
+        pub fn set_sender_access(&mut self, sender: AccountId, access: Option<SenderAccess>) {
+            let receiver = self.env().caller();
+
+            match access {
+                Some(access) => self.senders_access.insert((receiver, sender), access),
+                None => self.senders_access.take(&(receiver, sender)),
+            };
+        }
+
+        /// Publish or rotate the encryption key of the caller and return its new version.
+        #[ink(message)]
+        pub fn set_encryption_key(&mut self, scheme: EncryptionScheme, public_key: [u8; 32]) -> Result<u32, Error> {
+            let receiver = self.env().caller();
+
+            if scheme == EncryptionScheme::None {
+                return Err(Error::InvalidEncryptionKey);
+            }
+
+            let key = EncryptionKey {
+                scheme,
+                public_key,
+                version: self.get_encryption_key_version(receiver) + 1,
+            };
+            self.encryption_keys.insert(receiver, key);
+
+            self.env().emit_event(SetEncryptionKey {
+                receiver,
+                key,
+            });
+
+            Ok(key.version)
+        }
+
+        #[ink(message)]
+        pub fn get_encryption_key(&self, receiver: AccountId) -> Option<EncryptionKey> {
+            self.encryption_keys.get(&receiver).copied()
+        }
+
+        /// Set the balance senders attach to every document of the caller.
+        #[ink(message)]
+        pub fn set_postage(&mut self, postage: Balance) {
+            let receiver = self.env().caller();
+            self.postages.insert(receiver, postage);
+        }
+
+        #[ink(message)]
+        pub fn get_postage(&self, receiver: AccountId) -> Balance {
+            *self.postages.get(&receiver).unwrap_or(&0)
+        }
+
+        #[ink(message)]
+        pub fn get_access_policy(&self, receiver: AccountId) -> AccessPolicy {
+            *self.access_policies.get(&receiver).unwrap_or(&AccessPolicy::Open)
+        }
+
+        #[ink(message)]
+        pub fn get_sender_access(&self, receiver: AccountId, sender: AccountId) -> Option<SenderAccess> {
+            self.senders_access.get(&(receiver, sender)).copied()
+        }
+
+        #[ink(message)]
+        pub fn calculate_pre_signed_document_hash(
+            &self,
+            sender: AccountId,
+            receiver: AccountId,
+            data: DocumentPayload,
+            in_reply_to: Option<u32>,
+            encryption_key_version: u32,
+            nonce: u64,
+        ) -> Hash {
+            let encoded = (
+                PRE_SIGNED_DOCUMENT_DOMAIN,
+                self.env().account_id(),
+                sender,
+                receiver,
+                data,
+                in_reply_to,
+                encryption_key_version,
+                nonce,
+            );
+
+            Hash::from(self.env().hash_encoded::<Blake2x256, _>(&encoded))
+        }
+
+        #[ink(message)]
+        pub fn calculate_pre_signed_opened_at_hash(
+            &self,
+            receiver: AccountId,
+            index: u32,
+            nonce: u64,
+        ) -> Hash {
+            let encoded = (
+                PRE_SIGNED_OPENED_AT_DOMAIN,
+                self.env().account_id(),
+                receiver,
+                index,
+                nonce,
+            );
+
+            Hash::from(self.env().hash_encoded::<Blake2x256, _>(&encoded))
+        }
+
+        #[ink(message)]
+        pub fn get_documents(&self, receiver: AccountId) -> Option<Vec<Document>> {
+            let count = self.get_documents_count(receiver);
+
+            if count == 0 {
+                return None;
+            }
+
+            (0..count)
+                .map(|index| self.get_document(receiver, index))
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn get_documents_page(&self, receiver: AccountId, start: u32, limit: u32) -> DocumentsPage {
+            self.get_documents_page_by(receiver, start, limit, Order::Ascending, DocumentsFilter::All)
+        }
+
+        /// Return up to `limit` documents matching the `filter`, starting at index `start`.
+        /// `limit` is clamped between 1 and `MAX_PAGE_SIZE`, so following `next` always
+        /// advances. In descending order `start` is clamped to the newest document, so `u32::MAX`
+        /// returns the first page of the inbox.
+        #[ink(message)]
+        pub fn get_documents_page_by(
+            &self,
+            receiver: AccountId,
+            start: u32,
+            limit: u32,
+            order: Order,
+            filter: DocumentsFilter,
+        ) -> DocumentsPage {
+            let count = self.get_documents_count(receiver);
+            let limit = limit.clamp(1, MAX_PAGE_SIZE);
+            let now = self.env().block_timestamp();
+
+            let mut documents = Vec::new();
+            let mut scanned = 0;
+            let mut cursor = match order {
+                Order::Ascending if start < count => Some(start),
+                Order::Descending if count > 0 => Some(core::cmp::min(start, count - 1)),
+                _ => None,
+            };
+
+            while let Some(index) = cursor {
+                if documents.len() as u32 == limit || scanned == MAX_PAGE_SCAN {
+                    break;
+                }
+
+                if let Some(document) = self.documents.get(&(receiver, index)) {
+                    if filter.matches(document, now) {
+                        documents.push((index, document.clone()));
+                    }
+                }
+
+                scanned += 1;
+                cursor = match order {
+                    Order::Ascending => index.checked_add(1).filter(|next| *next < count),
+                    Order::Descending => index.checked_sub(1),
+                };
+            }
+
+            DocumentsPage {
+                documents,
+                next: cursor,
+            }
+        }
+
+        #[ink(message)]
+        pub fn get_document(&self, receiver: AccountId, index: u32) -> Option<Document> {
+            let doc = self.documents.get(&(receiver, index))?;
+            Some(doc.clone())
+        }
+
+        /// Return the payload of the document, resolving the content shared by the receivers
+        /// of a document sent to many receivers.
+        #[ink(message)]
+        pub fn get_document_payload(&self, receiver: AccountId, index: u32) -> Option<DocumentPayload> {
+            let doc = self.documents.get(&(receiver, index))?;
+
+            match &doc.data {
+                DocumentData::Payload(payload) => Some(payload.clone()),
+                DocumentData::Content { id, .. } => self.get_content(*id),
+            }
+        }
+
+        #[ink(message)]
+        pub fn get_content(&self, id: u32) -> Option<DocumentPayload> {
+            self.contents.get(&id).cloned()
+        }
+
+        #[ink(message)]
+        pub fn get_document_by_id(&self, id: Hash) -> Option<Document> {
+            let (receiver, index) = self.get_document_location(id)?;
+            self.get_document(receiver, index)
+        }
+
+        /// Return the receiver and index of the document with the global `id`.
+        #[ink(message)]
+        pub fn get_document_location(&self, id: Hash) -> Option<(AccountId, u32)> {
+            self.document_ids.get(&id).copied()
+        }
+
+        #[ink(message)]
+        pub fn get_document_status(&self, receiver: AccountId, index: u32) -> Option<DocumentStatus> {
+            let doc = self.documents.get(&(receiver, index))?;
+            Some(doc.status(self.env().block_timestamp()))
+        }
+
+        #[ink(message)]
+        pub fn get_documents_count(&self, receiver: AccountId) -> u32 {
+            *self.documents_count.get(&receiver).unwrap_or(&0)
+        }
+
+        #[ink(message)]
+        pub fn get_sent_document_reference(&self, sender: AccountId, index: u32) -> Option<(AccountId, u32)> {
+            self.sent_documents.get(&(sender, index)).copied()
+        }
+
+        #[ink(message)]
+        pub fn get_sent_document(&self, sender: AccountId, index: u32) -> Option<Document> {
+            let (receiver, receiver_index) = self.get_sent_document_reference(sender, index)?;
+            self.get_document(receiver, receiver_index)
+        }
+
+        #[ink(message)]
+        pub fn get_sent_documents_count(&self, sender: AccountId) -> u32 {
+            *self.sent_documents_count.get(&sender).unwrap_or(&0)
+        }
+
+        /// Store the document and transfer the `postage` attached by the sender to the receiver.
+        /// Every check runs before the first write, so an error leaves the storage untouched.
+        #[allow(clippy::too_many_arguments)]
+        fn store_document(
+            &mut self,
+            sender: AccountId,
+            receiver: AccountId,
+            data: DocumentData,
+            in_reply_to: Option<u32>,
+            forwarded_from: Option<ForwardedFrom>,
+            encryption_key_version: u32,
+            postage: Balance,
+        ) -> Result<(), Error> {
+            self.check_document(sender, receiver, &data, in_reply_to, encryption_key_version, postage)?;
+
+            // Pay the postage before any write, so a failed transfer leaves the storage untouched
+            if postage > 0 {
+                self.env().transfer(receiver, postage)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+
+            self.insert_document(sender, receiver, data, in_reply_to, forwarded_from);
+
+            Ok(())
+        }
+
+        /// Write the checked document to the storage of the `receiver` and the outbox of the `sender`.
+        fn insert_document(
+            &mut self,
+            sender: AccountId,
+            receiver: AccountId,
+            data: DocumentData,
+            in_reply_to: Option<u32>,
+            forwarded_from: Option<ForwardedFrom>,
+        ) {
+            let index = self.get_documents_count(receiver);
+            let sent_at = self.env().block_timestamp();
+
+            let id = Hash::from(self.env().hash_encoded::<Blake2x256, _>(&(sender, receiver, index, &data)));
+            let encryption_key_version = self.get_encryption_key_version(receiver);
+
+            self.documents.insert((receiver, index), Document {
+                sender,
+                data: data.clone(),
+                sent_at,
+                opened_at: Timestamp::default(),
+                revoked_at: Timestamp::default(),
+                receipt: None,
+                in_reply_to,
+                id,
+                encryption_key_version,
+                forwarded_from,
+                receiver_metadata: ReceiverMetadata::default(),
+            });
+            self.documents_count.insert(receiver, index + 1);
+            self.document_ids.insert(id, (receiver, index));
+
+            let sent_index = self.get_sent_documents_count(sender);
+            self.sent_documents.insert((sender, sent_index), (receiver, index));
+            self.sent_documents_count.insert(sender, sent_index + 1);
+
+            self.env().emit_event(AddDocument {
+                sender,
+                receiver,
+                data,
+                sent_at,
+            });
+        }
+
+        /// Check the `sender` can add the document encrypted to the `encryption_key_version` to
+        /// the `receiver` attaching the `postage`.
+        fn check_document(
+            &self,
+            sender: AccountId,
+            receiver: AccountId,
+            data: &DocumentData,
+            in_reply_to: Option<u32>,
+            encryption_key_version: u32,
+            postage: Balance,
+        ) -> Result<(), Error> {
+            let sender_access = self.get_sender_access(receiver, sender);
+
+            if !self.get_access_policy(receiver).allows(sender_access) {
+                return Err(Error::SenderNotAllowed);
+            }
+
+            // The sender encrypted the document to a key the receiver rotated meanwhile
+            if encryption_key_version != self.get_encryption_key_version(receiver) {
+                return Err(Error::EncryptionKeyVersionMismatch);
+            }
+
+            if postage < self.get_postage(receiver) {
+                return Err(Error::InsufficientPostage);
+            }
+
+            // Only documents the sender received can be replied to
+            if let Some(replied_index) = in_reply_to {
+                if !self.documents.contains_key(&(sender, replied_index)) {
+                    return Err(Error::NotExist);
+                }
+            }
+
+            if matches!(data.expires_at(), Some(expires_at) if expires_at <= self.env().block_timestamp()) {
+                return Err(Error::Expired);
+            }
+
+            Ok(())
+        }
+
+        fn open_document(&mut self, receiver: AccountId, index: u32, opened_by: AccountId) -> Result<(), Error> {
+            let opened_at = self.env().block_timestamp();
+
+            let doc = self.documents.get_mut(&(receiver, index))
+                .ok_or(Error::NotExist)?;
+
+            match doc.status(opened_at) {
+                DocumentStatus::Unopened => {}
+                DocumentStatus::Opened => return Err(Error::AlreadyOpened),
+                DocumentStatus::Revoked => return Err(Error::Revoked),
+                DocumentStatus::Expired => return Err(Error::Expired),
+            }
+
+            doc.opened_at = opened_at;
+
+            self.env().emit_event(SetOpenedAt {
+                receiver,
+                opened_by,
+                index,
+                opened_at,
+            });
+
+            Ok(())
+        }
+
+        fn issue_receipt(
+            &mut self,
+            receiver: AccountId,
+            index: u32,
+            status: ReceiptStatus,
+            reason_hash: Option<Hash>,
+        ) -> Result<(), Error> {
+            let issued_at = self.env().block_timestamp();
+
+            let doc = self.documents.get(&(receiver, index))
+                .ok_or(Error::NotExist)?;
+
+            if doc.receipt.is_some() {
+                return Err(Error::ReceiptAlreadyIssued);
+            }
+
+            if doc.status(issued_at) != DocumentStatus::Opened {
+                self.open_document(receiver, index, receiver)?;
+            }
+
+            let doc = self.documents.get_mut(&(receiver, index))
+                .ok_or(Error::NotExist)?;
+
+            let receipt = Receipt {
+                status,
+                reason_hash,
+                issued_at,
+            };
+            doc.receipt = Some(receipt.clone());
+            let sender = doc.sender;
+
+            self.env().emit_event(IssueReceipt {
+                sender,
+                receiver,
+                index,
+                receipt,
+            });
+
+            Ok(())
+        }
+
+        /// The signer signs the hash with a substrate ecdsa key, which hashes the message
+        /// with blake2-256 before signing. The account id is the blake2-256 hash of the
+        /// compressed public key.
+        fn is_valid_signature(&self, signer: AccountId, hash: Hash, signature: &[u8]) -> bool {
+            if signature.len() != 65 {
+                return false;
+            }
+
+            let mut signature_bytes = [0_u8; 65];
+            signature_bytes.copy_from_slice(signature);
+
+            let message_hash = self.env().hash_bytes::<Blake2x256>(hash.as_ref());
+
+            match self.env().ecdsa_recover(&signature_bytes, &message_hash) {
+                Ok(public_key) => {
+                    let account_id = self.env().hash_bytes::<Blake2x256>(public_key.as_ref());
+                    AccountId::from(account_id) == signer
+                }
+                Err(_) => false,
+            }
+        }
+
+        /// Return the global ID of the document if the `party` is its receiver or sender.
+        fn get_document_id_of_party(&self, receiver: AccountId, index: u32, party: AccountId) -> Result<Hash, Error> {
+            let doc = self.documents.get(&(receiver, index))
+                .ok_or(Error::NotExist)?;
+
+            if party != receiver && party != doc.sender {
+                return Err(Error::NotAllowed);
+            }
+
+            Ok(doc.id)
+        }
+
+        fn get_encryption_key_version(&self, receiver: AccountId) -> u32 {
+            self.get_encryption_key(receiver).map_or(0, |key| key.version)
+        }
+
+        fn is_active_delegate(&self, receiver: AccountId, delegate: AccountId) -> bool {
+            match self.delegates.get(&(receiver, delegate)) {
+                Some(Some(expires_at)) => self.env().block_timestamp() < *expires_at,
+                Some(None) => true,
+                None => false,
+            }
+        }
+
+        /// Run the payable message and return the balance attached by the caller when the
+        /// message fails, as an error does not revert the transfer of the attached balance.
+        /// The message must fail before it writes to the storage or transfers any balance.
+        fn refund_on_error<T>(&mut self, message: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+            let result = message(self);
+
+            if result.is_err() {
+                let caller = self.env().caller();
+                let value = self.env().transferred_balance();
+
+                if value > 0 && self.env().transfer(caller, value).is_err() {
+                    panic!("the attached balance could not be refunded");
+                }
+            }
+
+            result
+        }
+
+        fn is_executed_settlement(&self, signer: AccountId, hash: Hash) -> bool {
+            self.executed_settlements.contains_key(&(signer, hash))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use ink_lang as ink;
+
+        use super::*;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("off-chain environment should have been initialized already")
+        }
+
+        fn account_balance(account: AccountId) -> Balance {
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(account)
+                .expect("Cannot get account balance")
+        }
+
+        fn set_account_balance(account: AccountId, balance: Balance) {
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(account, balance)
+                .expect("Cannot set account balance");
+        }
+
+        /// Create the contract and execute the following messages as alice without attached balance
+        fn new_contract() -> Documents {
+            push_caller(default_accounts().alice);
+            Documents::new()
+        }
+
+        /// Execute the following messages as the `caller` until the execution context is popped
+        fn push_caller(caller: AccountId) {
+            push_caller_with_value(caller, 0);
+        }
+
+        /// Execute the following messages as the `caller` attaching the `value` until the execution
+        /// context is popped. The value moves from the caller to the contract like on chain.
+        fn push_caller_with_value(caller: AccountId, value: Balance) {
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or_else(|_| [0x0; 32].into());
+
+            set_account_balance(caller, account_balance(caller) - value);
+            set_account_balance(callee, account_balance(callee) + value);
+
+            let mut data = ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            data.push_arg(&caller);
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                caller,
+                callee,
+                1000000,
+                value,
+                data,
+            );
+        }
+
+        fn document_payload() -> DocumentPayload {
+            DocumentPayload {
+                version: DocumentPayload::VERSION,
+                uri: String::from("https://emn178.github.io/online-tools/sha256.html"),
+                hash_algorithm: HashAlgorithm::Sha256,
+                digest: vec![
+                    0xe2, 0xc1, 0xfc, 0xbd, 0x5b, 0x4b, 0xef, 0xac, 0xb2, 0xeb, 0xdc, 0x5a, 0x7b, 0x6e, 0x6d, 0xa8,
+                    0x6a, 0xd5, 0xb2, 0xa1, 0xeb, 0xb5, 0x03, 0x71, 0xa5, 0x46, 0xd1, 0x97, 0x46, 0x71, 0x65, 0xc9,
+                ],
+                mime_type: String::from("text/html"),
+                size: 1024,
+                encryption_scheme: EncryptionScheme::None,
+                expires_at: None,
+            }
+        }
+
+        fn add_document(contract: &mut Documents, receiver: AccountId) -> Result<(), Error> {
+            let encryption_key_version = contract.get_encryption_key(receiver).map_or(0, |key| key.version);
+            contract.add_document(receiver, document_payload(), None, encryption_key_version)
+        }
+
+        /// Push the contract to the storage at the root key. The contract is forgotten like after
+        /// a call on chain, since dropping the storage collections clears their storage.
+        fn push_contract(contract: Documents) {
+            ink_storage::traits::push_spread_root(&contract, &ink_primitives::Key::from([0x00; 32]));
+            core::mem::forget(contract);
+        }
+
+        fn used_storage_cells() -> usize {
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract account");
+
+            ink_env::test::count_used_storage_cells::<ink_env::DefaultEnvironment>(&callee)
+                .expect("Cannot count used storage cells")
+        }
+
+        /// Execute the message on the contract stored at the root key like on chain, pulling the
+        /// contract before and pushing it after, and return the storage reads and writes it made
+        fn execute_on_storage<R>(message: impl FnOnce(&mut Documents) -> R) -> (R, (usize, usize)) {
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract account");
+            let storage_rw = || {
+                ink_env::test::get_contract_storage_rw::<ink_env::DefaultEnvironment>(&callee)
+                    .expect("Cannot get storage reads and writes")
+            };
+
+            let (reads_before, writes_before) = storage_rw();
+            let mut contract: Documents = ink_storage::traits::pull_spread_root(&ink_primitives::Key::from([0x00; 32]));
+            let result = message(&mut contract);
+            push_contract(contract);
+            let (reads_after, writes_after) = storage_rw();
+
+            (result, (reads_after - reads_before, writes_after - writes_before))
+        }
+
+        /// Create the ecdsa key of a signer together with its account id, the blake2-256 hash of
+        /// the compressed public key
+        fn ecdsa_signer(seed: u8) -> (secp256k1::SecretKey, AccountId) {
+            let secret_key = secp256k1::SecretKey::parse(&[seed; 32]).expect("Cannot parse secret key");
+            let public_key = secp256k1::PublicKey::from_secret_key(&secret_key);
+
+            let mut account_id = [0_u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&public_key.serialize_compressed(), &mut account_id);
+
+            (secret_key, AccountId::from(account_id))
+        }
+
+        /// Sign the hash like a substrate ecdsa key, which hashes the message with blake2-256
+        fn ecdsa_sign(secret_key: &secp256k1::SecretKey, hash: Hash) -> Vec<u8> {
+            let mut message_hash = [0_u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(hash.as_ref(), &mut message_hash);
+
+            let (signature, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&message_hash), secret_key);
+
+            let mut signature_bytes = signature.serialize().to_vec();
+            signature_bytes.push(recovery_id.serialize());
+            signature_bytes
+        }
+
+        #[ink::test]
+        fn adding_documents_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(contract.get_documents_count(default_accounts.alice), 0);
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(ink_env::test::recorded_events().count(), 1);
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(contract.get_documents_count(default_accounts.alice), 2);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
+        }
+
+        #[ink::test]
+        fn adding_invalid_document_fails() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            let mut payload = document_payload();
+            payload.digest.pop();
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Err(Error::InvalidPayload));
+
+            let mut payload = document_payload();
+            payload.version = 0;
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Err(Error::InvalidPayload));
+
+            let mut payload = document_payload();
+            payload.uri = String::new();
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Err(Error::InvalidPayload));
+
+            assert_eq!(contract.get_documents_count(default_accounts.alice), 0);
+            assert_eq!(ink_env::test::recorded_events().count(), 0);
+        }
+
+        #[ink::test]
+        fn adding_documents_keeps_previous_documents_untouched() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            for _ in 0..100 {
+                assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            }
+
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 42), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            assert_eq!(contract.get_documents_count(default_accounts.alice), 100);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
+            assert_eq!(contract.get_document(default_accounts.alice, 99).is_some(), true);
+            assert_eq!(contract.get_document(default_accounts.alice, 100).is_none(), true);
+            assert_eq!(contract.get_document(default_accounts.bob, 0).unwrap().data, DocumentData::Payload(document_payload()));
+            assert_eq!(contract.get_documents(default_accounts.alice).unwrap().len(), 100);
+        }
+
+        #[ink::test]
+        fn access_policy_filters_senders() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let alice = default_accounts.alice;
+
+            assert_eq!(contract.get_access_policy(alice), AccessPolicy::Open);
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+
+            contract.set_access_policy(AccessPolicy::Allowlist);
+            assert_eq!(add_document(&mut contract, alice), Err(Error::SenderNotAllowed));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            contract.set_sender_access(alice, Some(SenderAccess::Allowed));
+            assert_eq!(contract.get_sender_access(alice, alice), Some(SenderAccess::Allowed));
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+
+            contract.set_access_policy(AccessPolicy::Blocklist);
+            contract.set_sender_access(alice, Some(SenderAccess::Blocked));
+            assert_eq!(add_document(&mut contract, alice), Err(Error::SenderNotAllowed));
+
+            contract.set_sender_access(alice, None);
+            assert_eq!(contract.get_sender_access(alice, alice), None);
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+
+            assert_eq!(contract.get_documents_count(alice), 3);
+            assert_eq!(contract.get_sent_documents_count(alice), 4);
+        }
+
+        #[ink::test]
+        fn adding_document_requires_postage() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            push_caller(default_accounts.bob);
+            contract.set_postage(10);
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_postage(default_accounts.bob), 10);
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Err(Error::InsufficientPostage));
+
+            let bob_balance = account_balance(default_accounts.bob);
+
+            push_caller_with_value(default_accounts.alice, 10);
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(account_balance(default_accounts.bob), bob_balance + 10);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
+        }
+
+        #[ink::test]
+        fn failing_payable_messages_refund_attached_balance() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let contract_id = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+
+            push_caller(default_accounts.bob);
+            contract.set_postage(10);
+            ink_env::test::pop_execution_context();
+
+            let alice_balance = account_balance(default_accounts.alice);
+
+            push_caller_with_value(default_accounts.alice, 5);
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Err(Error::InsufficientPostage));
+            ink_env::test::pop_execution_context();
+
+            push_caller_with_value(default_accounts.alice, 5);
+            assert_eq!(contract.forward_document(0, default_accounts.bob, 0), Err(Error::NotExist));
+            ink_env::test::pop_execution_context();
+
+            push_caller_with_value(default_accounts.alice, 5);
+            assert_eq!(
+                contract.add_pre_signed_document(
+                    default_accounts.alice,
+                    default_accounts.bob,
+                    document_payload(),
+                    None,
+                    0,
+                    0,
+                    vec![0_u8; 65],
+                ),
+                Err(Error::InvalidSignature),
+            );
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(account_balance(default_accounts.alice), alice_balance);
+            assert_eq!(account_balance(contract_id), 0);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn adding_document_to_many_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let receivers = vec![(default_accounts.bob, 0), (default_accounts.charlie, 0)];
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(contract.add_document_to_many(Vec::new(), document_payload(), None), Err(Error::InvalidReceivers));
+            assert_eq!(contract.add_document_to_many(receivers.clone(), document_payload(), None), Ok(0));
+            assert_eq!(contract.get_content(0), Some(document_payload()));
+            assert_eq!(ink_env::test::recorded_events().count(), 3);
+
+            for (receiver, _) in receivers {
+                let document = contract.get_document(receiver, 0).unwrap();
+                assert_eq!(document.data, DocumentData::Content { id: 0, expires_at: None });
+                assert_eq!(contract.get_document_payload(receiver, 0), Some(document_payload()));
+            }
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.set_opened_at(default_accounts.bob, 0), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_document_status(default_accounts.bob, 0), Some(DocumentStatus::Opened));
+            assert_eq!(contract.get_document_status(default_accounts.charlie, 0), Some(DocumentStatus::Unopened));
+            assert_eq!(contract.get_sent_documents_count(default_accounts.alice), 2);
+            assert_eq!(contract.get_sent_document_reference(default_accounts.alice, 1), Some((default_accounts.charlie, 0)));
+        }
+
+        #[ink::test]
+        fn adding_document_to_many_checks_every_receiver_first() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let receivers = vec![(default_accounts.bob, 0), (default_accounts.charlie, 0)];
+
+            push_caller(default_accounts.charlie);
+            contract.set_postage(10);
+            ink_env::test::pop_execution_context();
+
+            let alice_balance = account_balance(default_accounts.alice);
+
+            push_caller_with_value(default_accounts.alice, 5);
+            assert_eq!(
+                contract.add_document_to_many(receivers.clone(), document_payload(), None),
+                Err(Error::InsufficientPostage),
+            );
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(account_balance(default_accounts.alice), alice_balance);
+            assert_eq!(contract.get_content(0), None);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 0);
+            assert_eq!(ink_env::test::recorded_events().count(), 0);
+
+            let charlie_balance = account_balance(default_accounts.charlie);
+
+            push_caller_with_value(default_accounts.alice, 15);
+            assert_eq!(contract.add_document_to_many(receivers, document_payload(), None), Ok(0));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(account_balance(default_accounts.alice), alice_balance - 10);
+            assert_eq!(account_balance(default_accounts.charlie), charlie_balance + 10);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "the postage could not be transferred to the receiver")]
+        fn adding_document_to_many_traps_when_later_transfer_fails() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let contract_id = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+
+            for receiver in [default_accounts.bob, default_accounts.charlie] {
+                push_caller(receiver);
+                contract.set_postage(10);
+                ink_env::test::pop_execution_context();
+            }
+
+            push_caller_with_value(default_accounts.alice, 20);
+
+            // Only the postage of the first receiver can be transferred
+            set_account_balance(contract_id, 10);
+
+            let _ = contract.add_document_to_many(
+                vec![(default_accounts.bob, 0), (default_accounts.charlie, 0)],
+                document_payload(),
+                None,
+            );
+        }
+
+        #[ink::test]
+        fn replying_to_document_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.add_document(default_accounts.alice, document_payload(), Some(0), 0), Err(Error::NotExist));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.add_document(default_accounts.alice, document_payload(), Some(0), 0), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_document(default_accounts.alice, 0).unwrap().in_reply_to, Some(0));
+            assert_eq!(contract.get_document(default_accounts.bob, 0).unwrap().in_reply_to, None);
+        }
+
+        #[ink::test]
+        fn forwarding_document_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.forward_document(0, default_accounts.charlie, 0), Err(Error::NotExist));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.forward_document(0, default_accounts.charlie, 0), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            let original = contract.get_document(default_accounts.bob, 0).unwrap();
+            let forwarded = contract.get_document(default_accounts.charlie, 0).unwrap();
+
+            assert_eq!(forwarded.sender, default_accounts.bob);
+            assert_eq!(forwarded.data, original.data);
+            assert_eq!(forwarded.forwarded_from, Some(ForwardedFrom {
+                receiver: default_accounts.bob,
+                index: 0,
+                sender: default_accounts.alice,
+                sent_at: original.sent_at,
+            }));
+            assert_eq!(contract.get_sent_document_reference(default_accounts.bob, 0), Some((default_accounts.charlie, 0)));
+
+            assert_eq!(contract.recall_document(default_accounts.bob, 0), Ok(()));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.forward_document(0, default_accounts.django, 0), Err(Error::Revoked));
+            ink_env::test::pop_execution_context();
+        }
+
+        #[ink::test]
+        fn setting_receiver_metadata_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let metadata = ReceiverMetadata {
+                archived: true,
+                pinned: true,
+                labels: 0b101,
+            };
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(contract.set_receiver_metadata(0, metadata), Err(Error::NotExist));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.set_receiver_metadata(0, metadata), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_document(default_accounts.bob, 0).unwrap().receiver_metadata, metadata);
+        }
+
+        #[ink::test]
+        fn retrieving_document_by_id_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            let first_id = contract.get_document(default_accounts.alice, 0).unwrap().id;
+            let second_id = contract.get_document(default_accounts.alice, 1).unwrap().id;
+
+            assert_ne!(first_id, second_id);
+            assert_eq!(contract.get_document_location(second_id), Some((default_accounts.alice, 1)));
+            assert_eq!(contract.get_document_by_id(first_id).unwrap().id, first_id);
+            assert_eq!(contract.get_document_by_id(Hash::from([0x00; 32])).is_none(), true);
+        }
+
+        #[ink::test]
+        fn rotating_encryption_key_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let alice = default_accounts.alice;
+
+            assert_eq!(contract.get_encryption_key(alice), None);
+            assert_eq!(contract.set_encryption_key(EncryptionScheme::None, [0x01; 32]), Err(Error::InvalidEncryptionKey));
+
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+            assert_eq!(contract.set_encryption_key(EncryptionScheme::X25519XSalsa20Poly1305, [0x01; 32]), Ok(1));
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+            assert_eq!(contract.set_encryption_key(EncryptionScheme::X25519Aes256Gcm, [0x02; 32]), Ok(2));
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+
+            assert_eq!(
+                contract.get_encryption_key(alice),
+                Some(EncryptionKey {
+                    scheme: EncryptionScheme::X25519Aes256Gcm,
+                    public_key: [0x02; 32],
+                    version: 2,
+                }),
+            );
+
+            for index in 0..3 {
+                assert_eq!(contract.get_document(alice, index).unwrap().encryption_key_version, index);
+            }
+
+            // Documents encrypted to a rotated key are rejected
+            assert_eq!(
+                contract.add_document(alice, document_payload(), None, 1),
+                Err(Error::EncryptionKeyVersionMismatch),
+            );
+            assert_eq!(
+                contract.add_document_to_many(vec![(alice, 2), (default_accounts.bob, 1)], document_payload(), None),
+                Err(Error::EncryptionKeyVersionMismatch),
+            );
+            assert_eq!(contract.get_documents_count(alice), 3);
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn granting_document_access_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let bob = default_accounts.bob;
+            let charlie = default_accounts.charlie;
+            let django = default_accounts.django;
+
+            assert_eq!(add_document(&mut contract, bob), Ok(()));
+
+            assert_eq!(contract.grant_document_access(bob, 0, charlie, Vec::new()), Err(Error::InvalidWrappedKey));
+            assert_eq!(contract.grant_document_access(bob, 0, charlie, vec![0x01; 48]), Ok(()));
+            assert_eq!(contract.grant_document_access(bob, 0, charlie, vec![0x01; 48]), Err(Error::KeyEnvelopeAlreadyExists));
+            assert_eq!(
+                contract.get_key_envelope(bob, 0, charlie),
+                Some(KeyEnvelope {
+                    granted_by: default_accounts.alice,
+                    wrapped_key: vec![0x01; 48],
+                }),
+            );
+
+            push_caller(bob);
+            assert_eq!(contract.grant_document_access(bob, 0, django, vec![0x02; 48]), Ok(()));
+            assert_eq!(contract.revoke_document_access(bob, 0, charlie), Ok(()));
+            assert_eq!(contract.revoke_document_access(bob, 0, charlie), Err(Error::KeyEnvelopeNotExist));
+            ink_env::test::pop_execution_context();
+
+            push_caller(charlie);
+            assert_eq!(contract.grant_document_access(bob, 0, charlie, vec![0x03; 48]), Err(Error::NotAllowed));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_key_envelope(bob, 0, charlie), None);
+            assert_eq!(contract.get_key_envelope(bob, 0, django).unwrap().granted_by, bob);
+        }
+
+        #[ink::test]
+        fn retrieving_sent_documents_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(contract.get_sent_documents_count(default_accounts.alice), 0);
+            assert_eq!(contract.get_sent_document(default_accounts.alice, 0).is_none(), true);
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.charlie), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+
+            assert_eq!(contract.get_sent_documents_count(default_accounts.alice), 3);
+            assert_eq!(contract.get_sent_documents_count(default_accounts.bob), 0);
+            assert_eq!(
+                contract.get_sent_document_reference(default_accounts.alice, 1),
+                Some((default_accounts.charlie, 0)),
+            );
+            assert_eq!(
+                contract.get_sent_document_reference(default_accounts.alice, 2),
+                Some((default_accounts.bob, 1)),
+            );
+            assert_eq!(
+                contract.get_sent_document(default_accounts.alice, 2).unwrap().sender,
+                default_accounts.alice,
+            );
+            assert_eq!(contract.get_sent_document_reference(default_accounts.alice, 3), None);
+        }
+
+        #[ink::test]
+        fn document_storage_access_does_not_grow_with_documents_count() {
+            let default_accounts = default_accounts();
+
+            push_contract(new_contract());
+
+            // The first document of the receiver creates its counter, so compare from the second one
+            let mut costs = Vec::new();
+
+            for documents_count in &[1, 20] {
+                while execute_on_storage(|contract| contract.get_documents_count(default_accounts.alice)).0 < *documents_count {
+                    let (result, _) = execute_on_storage(|contract| add_document(contract, default_accounts.alice));
+                    assert_eq!(result, Ok(()));
+                }
+
+                // Every document is stored in new cells instead of growing a cell of the receiver
+                let cells_before = used_storage_cells();
+                let (result, add_cost) = execute_on_storage(|contract| add_document(contract, default_accounts.alice));
+                assert_eq!(result, Ok(()));
+                let added_cells = used_storage_cells() - cells_before;
+                assert_eq!(added_cells > 0, true);
+
+                let (document, get_cost) = execute_on_storage(|contract| {
+                    contract.get_document(default_accounts.alice, *documents_count)
+                });
+                assert_eq!(document.is_some(), true);
+
+                costs.push((add_cost, added_cells, get_cost));
+            }
+
+            assert_eq!(costs[0], costs[1]);
+        }
+
+        #[ink::test]
+        fn retrieving_documents_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(contract.get_documents(default_accounts.alice).is_none(), true);
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(contract.get_documents(default_accounts.alice).is_some(), true);
+            assert_eq!(contract.get_documents(default_accounts.alice).unwrap().len(), 2);
+        }
+
+        #[ink::test]
+        fn retrieving_documents_page_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            let page = contract.get_documents_page(default_accounts.alice, 0, 10);
+            assert_eq!(page.documents.len(), 0);
+            assert_eq!(page.next, None);
+
+            for _ in 0..5 {
+                assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            }
+
+            let page = contract.get_documents_page(default_accounts.alice, 0, 2);
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![0, 1]);
+            assert_eq!(page.next, Some(2));
+
+            let page = contract.get_documents_page(default_accounts.alice, 2, 0);
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![2]);
+            assert_eq!(page.next, Some(3));
+
+            let page = contract.get_documents_page(default_accounts.alice, 4, 2);
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![4]);
+            assert_eq!(page.next, None);
+
+            let page = contract.get_documents_page(default_accounts.alice, 5, 2);
+            assert_eq!(page.documents.len(), 0);
+            assert_eq!(page.next, None);
+        }
+
+        #[ink::test]
+        fn retrieving_documents_page_in_descending_order_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            for _ in 0..5 {
+                assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            }
+
+            let page = contract.get_documents_page_by(
+                default_accounts.alice, u32::MAX, 3, Order::Descending, DocumentsFilter::All,
+            );
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![4, 3, 2]);
+            assert_eq!(page.next, Some(1));
+
+            let page = contract.get_documents_page_by(
+                default_accounts.alice, 1, 3, Order::Descending, DocumentsFilter::All,
+            );
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 0]);
+            assert_eq!(page.next, None);
+        }
+
+        #[ink::test]
+        fn retrieving_filtered_documents_page_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            let now = ink_env::block_timestamp::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get block timestamp");
+
+            for _ in 0..5 {
+                assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            }
+
+            let mut payload = document_payload();
+            payload.expires_at = Some(now + 1);
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Ok(()));
+
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 1), Ok(()));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 3), Ok(()));
+            assert_eq!(contract.recall_document(default_accounts.alice, 0), Ok(()));
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            let page = contract.get_documents_page_by(
+                default_accounts.alice, 0, 10, Order::Ascending, DocumentsFilter::Opened,
+            );
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 3]);
+
+            let page = contract.get_documents_page_by(
+                default_accounts.alice, u32::MAX, 2, Order::Descending, DocumentsFilter::Unopened,
+            );
+            assert_eq!(page.documents.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![4, 2]);
+            assert_eq!(page.next, Some(1));
+
+            // Revoked and expired documents are not unopened
+            let page = contract.get_documents_page_by(
+                default_accounts.alice, 1, 2, Order::Descending, DocumentsFilter::Unopened,
+            );
+            assert!(page.documents.is_empty());
+            assert_eq!(page.next, None);
+        }
+
+        #[ink::test]
+        fn retrieving_document_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(contract.get_document(default_accounts.alice, 0).is_none(), true);
+            assert_eq!(contract.get_document(default_accounts.alice, 1).is_none(), true);
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(contract.get_document(default_accounts.alice, 0).is_some(), true);
+        }
+
+        #[ink::test]
+        fn setting_opened_at_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(contract.set_opened_at(default_accounts.bob, 0), Err(Error::NotAllowed));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Err(Error::NotExist));
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Ok(()));
+            assert_eq!(ink_env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn setting_opened_at_by_delegate_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(contract.set_opened_at(default_accounts.bob, 0), Err(Error::NotAllowed));
+
+            push_caller(default_accounts.bob);
+            contract.add_delegate(default_accounts.alice, None);
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_delegate(default_accounts.bob, default_accounts.alice), Some(None));
+            assert_eq!(contract.set_opened_at(default_accounts.bob, 0), Ok(()));
+
+            // Delegate expired at the genesis block
+            push_caller(default_accounts.bob);
+            contract.add_delegate(default_accounts.alice, Some(0));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.set_opened_at(default_accounts.bob, 1), Err(Error::NotAllowed));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(contract.remove_delegate(default_accounts.alice), Ok(()));
+            assert_eq!(contract.remove_delegate(default_accounts.alice), Err(Error::DelegateNotExist));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.get_delegate(default_accounts.bob, default_accounts.alice), None);
+            assert_eq!(contract.get_document(default_accounts.bob, 1).unwrap().opened_at, 0);
+        }
+
+        #[ink::test]
+        fn recalling_document_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(contract.get_document_status(default_accounts.alice, 0), Some(DocumentStatus::Unopened));
+
+            assert_eq!(contract.recall_document(default_accounts.alice, 0), Ok(()));
+            assert_eq!(contract.recall_document(default_accounts.alice, 0), Err(Error::Revoked));
+            assert_eq!(contract.get_document_status(default_accounts.alice, 0), Some(DocumentStatus::Revoked));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Err(Error::Revoked));
+
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 1), Ok(()));
+            assert_eq!(contract.recall_document(default_accounts.alice, 1), Err(Error::AlreadyOpened));
+            assert_eq!(contract.recall_document(default_accounts.alice, 2), Err(Error::NotExist));
+
+            push_caller(default_accounts.bob);
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(contract.recall_document(default_accounts.alice, 2), Err(Error::NotAllowed));
+        }
+
+        #[ink::test]
+        fn expired_document_can_not_be_opened() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+            let now = ink_env::block_timestamp::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get block timestamp");
+
+            let mut payload = document_payload();
+            payload.expires_at = Some(now);
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Err(Error::Expired));
+
+            let mut payload = document_payload();
+            payload.expires_at = Some(now + 1);
+            assert_eq!(contract.add_document(default_accounts.alice, payload, None, 0), Ok(()));
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(contract.get_document_status(default_accounts.alice, 0), Some(DocumentStatus::Expired));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Err(Error::Expired));
+        }
+
+        #[ink::test]
+        fn issuing_receipts_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let alice = default_accounts.alice;
+            let reason_hash = Hash::from([0x07; 32]);
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+            assert_eq!(add_document(&mut contract, alice), Ok(()));
+
+            assert_eq!(contract.acknowledge_document(0, Some(reason_hash)), Ok(()));
+            assert_eq!(contract.get_document_status(alice, 0), Some(DocumentStatus::Opened));
+            assert_eq!(
+                contract.get_document(alice, 0).unwrap().receipt.map(|receipt| (receipt.status, receipt.reason_hash)),
+                Some((ReceiptStatus::Acknowledged, Some(reason_hash))),
+            );
+
+            assert_eq!(contract.set_opened_at(alice, 1), Ok(()));
+            assert_eq!(contract.reject_document(1, None), Ok(()));
+            assert_eq!(
+                contract.get_document(alice, 1).unwrap().receipt.map(|receipt| (receipt.status, receipt.reason_hash)),
+                Some((ReceiptStatus::Rejected, None)),
+            );
+
+            assert_eq!(contract.reject_document(0, None), Err(Error::ReceiptAlreadyIssued));
+            assert_eq!(contract.acknowledge_document(2, None), Err(Error::NotExist));
+        }
+
+        #[ink::test]
+        fn adding_pre_signed_document_with_invalid_signature_fails() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(
+                contract.add_pre_signed_document(
+                    default_accounts.alice,
+                    default_accounts.bob,
+                    document_payload(),
+                    None,
+                    0,
+                    0,
+                    vec![0_u8; 65],
+                ),
+                Err(Error::InvalidSignature),
+            );
+            assert_eq!(
+                contract.add_pre_signed_document(
+                    default_accounts.alice,
+                    default_accounts.bob,
+                    document_payload(),
+                    None,
+                    0,
+                    0,
+                    vec![0_u8; 64],
+                ),
+                Err(Error::InvalidSignature),
+            );
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn adding_pre_signed_document_works() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+            let (secret_key, sender) = ecdsa_signer(0x01);
+
+            let hash = contract.calculate_pre_signed_document_hash(sender, default_accounts.bob, document_payload(), None, 0, 0);
+            let signature = ecdsa_sign(&secret_key, hash);
+
+            assert_eq!(
+                contract.add_pre_signed_document(sender, default_accounts.bob, document_payload(), None, 0, 0, signature.clone()),
+                Ok(()),
+            );
+            assert_eq!(contract.get_document(default_accounts.bob, 0).unwrap().sender, sender);
+
+            // Replaying the same signature fails
+            assert_eq!(
+                contract.add_pre_signed_document(sender, default_accounts.bob, document_payload(), None, 0, 0, signature),
+                Err(Error::AlreadyExecuted),
+            );
+            assert_eq!(contract.get_documents_count(default_accounts.bob), 1);
+        }
+
+        #[ink::test]
+        fn setting_pre_signed_opened_at_works() {
+            let mut contract = new_contract();
+            let (secret_key, receiver) = ecdsa_signer(0x02);
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(add_document(&mut contract, receiver), Ok(()));
+
+            let hash = contract.calculate_pre_signed_opened_at_hash(receiver, 0, 0);
+            let signature = ecdsa_sign(&secret_key, hash);
+
+            assert_eq!(contract.set_pre_signed_opened_at(receiver, 0, 0, signature.clone()), Ok(()));
+            assert_eq!(contract.get_document_status(receiver, 0), Some(DocumentStatus::Opened));
+
+            // Replaying the same signature fails
+            assert_eq!(
+                contract.set_pre_signed_opened_at(receiver, 0, 0, signature),
+                Err(Error::AlreadyExecuted),
+            );
+        }
+
+        #[ink::test]
+        fn setting_pre_signed_opened_at_with_invalid_signature_fails() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            assert_eq!(add_document(&mut contract, default_accounts.bob), Ok(()));
+            assert_eq!(
+                contract.set_pre_signed_opened_at(default_accounts.bob, 0, 0, vec![0_u8; 65]),
+                Err(Error::InvalidSignature),
+            );
+            assert_eq!(contract.get_document(default_accounts.bob, 0).unwrap().opened_at, 0);
+        }
+
+        #[ink::test]
+        fn pre_signed_hashes_are_domain_separated() {
+            let contract = new_contract();
+            let default_accounts = default_accounts();
+
+            let document_hash = contract.calculate_pre_signed_document_hash(
+                default_accounts.alice,
+                default_accounts.bob,
+                document_payload(),
+                None,
+                0,
+                0,
+            );
+
+            assert_ne!(
+                document_hash,
+                contract.calculate_pre_signed_document_hash(
+                    default_accounts.alice,
+                    default_accounts.bob,
+                    document_payload(),
+                    None,
+                    0,
+                    1,
+                ),
+            );
+            assert_ne!(
+                document_hash,
+                contract.calculate_pre_signed_opened_at_hash(default_accounts.bob, 0, 0),
+            );
+        }
+
+        #[ink::test]
+        fn setting_opened_at_twice_fails() {
+            let mut contract = new_contract();
+            let default_accounts = default_accounts();
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(add_document(&mut contract, default_accounts.alice), Ok(()));
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Ok(()));
+            let opened_at = contract.get_document(default_accounts.alice, 0).unwrap().opened_at;
+
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            assert_eq!(contract.set_opened_at(default_accounts.alice, 0), Err(Error::AlreadyOpened));
+            assert_eq!(contract.get_document(default_accounts.alice, 0).unwrap().opened_at, opened_at);
             assert_eq!(ink_env::test::recorded_events().count(), 2);
         }
     }
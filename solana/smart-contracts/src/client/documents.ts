@@ -14,7 +14,13 @@ import fs from 'mz/fs';
 import path from 'path';
 
 import {createKeypairFromFile, getPayer, getRpcUrl} from './utils';
-import {Receiver} from './documents/state';
+import {
+  DocumentPayload,
+  EncryptionScheme,
+  HashAlgorithm,
+  Receiver,
+} from './documents/state';
+import BN from 'bn.js';
 import {Service} from './documents/Service';
 
 /**
@@ -181,15 +187,33 @@ export async function checkProgram(): Promise<void> {
 export async function sendDocument(): Promise<void> {
   const receiverWalletAddress = new PublicKey('93Yp51XzFHfaPY7aJUFg5tmeijLmg3Ai9nGhnaiUPgiK');
 
-  const documentData = '0x18747470733a2f2f656d6e3137382e6769746875622e696f2f6f6e6c696e652d746f6f6c732f7368613235362e68746d6ce2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d197467165c9';
-  const documentData2 = '0x28747470733a2f2f656d6e3137382e6769746875622e696f2f6f6e6c696e652d746f6f6c732f7368613235362e68746d6ce2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d197467165c9';
+  const documentData = new DocumentPayload({
+    version: DocumentPayload.VERSION,
+    uri: 'https://emn178.github.io/online-tools/sha256.html',
+    hash_algorithm: HashAlgorithm.Sha256,
+    digest: Buffer.from('e2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d197467165c9', 'hex'),
+    mime_type: 'text/html',
+    size: new BN(1024),
+    encryption_scheme: EncryptionScheme.None,
+    expires_at: null,
+  });
+  const documentData2 = new DocumentPayload({
+    version: DocumentPayload.VERSION,
+    uri: 'https://emn178.github.io/online-tools/sha512.html',
+    hash_algorithm: HashAlgorithm.Sha512,
+    digest: Buffer.alloc(64, 0x28),
+    mime_type: 'application/pdf',
+    size: new BN(2048),
+    encryption_scheme: EncryptionScheme.X25519XSalsa20Poly1305,
+    expires_at: null,
+  });
 
   // Init service
   const service = new Service(connection, programId, payer);
 
   // Send documents
-  const txSignature = await service.sendDocument(receiverWalletAddress, Buffer.from(documentData));
-  const txSignature2 = await service.sendDocument(receiverWalletAddress, Buffer.from(documentData2));
+  const txSignature = await service.sendDocument(receiverWalletAddress, documentData);
+  const txSignature2 = await service.sendDocument(receiverWalletAddress, documentData2);
 
   console.log(txSignature);
 }
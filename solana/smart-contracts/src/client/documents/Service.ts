@@ -10,7 +10,16 @@ import {
   TransactionInstruction,
   TransactionSignature,
 } from '@solana/web3.js';
-import {Document, Receiver} from './state';
+import {
+  Document,
+  DocumentData,
+  DocumentLocator,
+  DocumentPayload,
+  Outbox,
+  Receiver,
+  SenderAccessEntry,
+  SentDocument,
+} from './state';
 import {
   DocumentsInstruction,
   Instruction,
@@ -22,7 +31,8 @@ import BN from 'bn.js';
 type SolDocument = {
   index: number,
   sender: string,
-  data: string,
+  data: DocumentPayload | null,
+  content: string | null,
   sent_at: string,
   opened_at: string,
 };
@@ -42,31 +52,72 @@ export class Service {
 
   public async sendDocument(
     receiverWalletAddress: PublicKey,
-    documentData: Uint8Array,
+    documentData: DocumentPayload,
+    inReplyTo: number | null = null,
   ): Promise<TransactionSignature> {
     console.log('Send document to', receiverWalletAddress.toBase58());
 
+    const senderAddress = this.payer.publicKey;
+
     // Create receiver PDA account
     const receiverPdaAddress = await this.createReceiverPdaAccount(receiverWalletAddress);
 
-    // Get next available document index
-    const documentIndex = await this.getDocumentsCounter(receiverPdaAddress);
+    // Get next available document index and the version of the key the document is encrypted to
+    const receiver = await this.getReceiver(receiverPdaAddress);
+    const documentIndex = receiver.documents_counter;
 
     // Get document PDA account address
     const documentPdaAddress = await Document.findPdaAddress(documentIndex, receiverWalletAddress, this.programId);
 
+    // Get document locator PDA account address from the global document ID
+    const documentId = Document.computeId(
+      senderAddress,
+      receiverWalletAddress,
+      documentIndex,
+      new DocumentData({payload: documentData}),
+    );
+    const documentLocatorPdaAddress = await DocumentLocator.findPdaAddress(documentId, this.programId);
+
+    // Get outbox PDA account address and the next available sent document index
+    const outboxPdaAddress = await Outbox.findPdaAddress(senderAddress, this.programId);
+    const sentDocumentIndex = await this.getSentDocumentsCounter(outboxPdaAddress);
+    const sentDocumentPdaAddress = await SentDocument.findPdaAddress(sentDocumentIndex, senderAddress, this.programId);
+
+    const senderAccessPdaAddress = await SenderAccessEntry.findPdaAddress(
+      receiverWalletAddress,
+      senderAddress,
+      this.programId,
+    );
+
+    const keys = [
+      {pubkey: this.payer.publicKey, isSigner: true, isWritable: true},
+      {pubkey: senderAddress, isSigner: true, isWritable: false},
+      {pubkey: receiverPdaAddress, isSigner: false, isWritable: true},
+      {pubkey: documentPdaAddress, isSigner: false, isWritable: true},
+      {pubkey: documentLocatorPdaAddress, isSigner: false, isWritable: true},
+      {pubkey: outboxPdaAddress, isSigner: false, isWritable: true},
+      {pubkey: sentDocumentPdaAddress, isSigner: false, isWritable: true},
+      {pubkey: senderAccessPdaAddress, isSigner: false, isWritable: false},
+      // Receiver wallet is credited with the postage of the receiver
+      {pubkey: receiverWalletAddress, isSigner: false, isWritable: true},
+      {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false},
+      {pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ];
+
+    if (inReplyTo !== null) {
+      const repliedDocumentPdaAddress = await Document.findPdaAddress(inReplyTo, senderAddress, this.programId);
+      keys.push({pubkey: repliedDocumentPdaAddress, isSigner: false, isWritable: false});
+    }
+
     // Send transaction
-    const instructionData = new InstructionData(DocumentsInstruction.SendDocument, {data: documentData}).encode();
+    const instructionData = new InstructionData(DocumentsInstruction.SendDocument, {
+      data: documentData,
+      in_reply_to: inReplyTo,
+      encryption_key_version: receiver.encryption_key.version,
+    }).encode();
     const instruction = new TransactionInstruction({
-      keys: [
-        {pubkey: this.payer.publicKey, isSigner: true, isWritable: true},
-        {pubkey: receiverPdaAddress, isSigner: false, isWritable: true},
-        {pubkey: documentPdaAddress, isSigner: false, isWritable: true},
-        {pubkey: receiverWalletAddress, isSigner: false, isWritable: false},
-        {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false},
-        {pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false},
-        {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
-      ],
+      keys,
       programId: this.programId,
       data: new Instruction({
         instruction: DocumentsInstruction.SendDocument,
@@ -90,7 +141,7 @@ export class Service {
     const receiverPdaAddress = await Receiver.findPdaAddress(receiverWalletAddress, this.programId);
 
     // Get number of documents
-    const documentsCounter = await this.getDocumentsCounter(receiverPdaAddress);
+    const documentsCounter = (await this.getReceiver(receiverPdaAddress)).documents_counter;
 
     console.log('-> Documents count:', documentsCounter);
 
@@ -100,6 +151,12 @@ export class Service {
     for (let i = 0; i < documentsCounter; i++) {
       const documentPdaAddress = await Document.findPdaAddress(i, receiverWalletAddress, this.programId);
       const document = await this.getDocument(documentPdaAddress);
+
+      // Skip closed documents
+      if (document === null) {
+        continue;
+      }
+
       document.index = i;
       documents.push(document);
     }
@@ -107,11 +164,15 @@ export class Service {
     return documents;
   }
 
-  private async getDocument(documentPdaAddress: PublicKey) {
+  private async getDocument(documentPdaAddress: PublicKey): Promise<SolDocument | null> {
     const accountInfo = await this.connection.getAccountInfo(documentPdaAddress);
 
     if (accountInfo === null) {
-      throw Error('Cannot find the document account');
+      return null;
+    }
+
+    if (accountInfo.data[0] !== Document.VERSION) {
+      throw Error('Unsupported document version');
     }
 
     const document = Document.decode<Document>(Document.schema, Document, accountInfo.data);
@@ -126,7 +187,8 @@ export class Service {
     return {
       index: 0,
       sender: new PublicKey(Buffer.from(sender ?? '')).toBase58(),
-      data: Buffer.from(data ?? '').toString(),
+      data: data?.payload ?? null,
+      content: data?.content ? new PublicKey(Buffer.from(data.content.address)).toBase58() : null,
       sent_at: new BN(sent_at ?? 0).toString(),
       opened_at: new BN(opened_at ?? 0).toString(),
     }
@@ -168,14 +230,25 @@ export class Service {
     return receiverPdaAddress;
   }
 
-  private async getDocumentsCounter(receiverPdaAddress: PublicKey): Promise<number> {
+  private async getReceiver(receiverPdaAddress: PublicKey): Promise<Receiver> {
     const accountInfo = await this.connection.getAccountInfo(receiverPdaAddress);
 
     if (accountInfo === null) {
       throw Error('Cannot find the receiver account');
     }
 
-    return Receiver.decode<Receiver>(Receiver.schema, Receiver, accountInfo.data)
+    return Receiver.decode<Receiver>(Receiver.schema, Receiver, accountInfo.data);
+  }
+
+  private async getSentDocumentsCounter(outboxPdaAddress: PublicKey): Promise<number> {
+    const accountInfo = await this.connection.getAccountInfo(outboxPdaAddress);
+
+    // The outbox is created with the first document of the sender
+    if (accountInfo === null) {
+      return 0;
+    }
+
+    return Outbox.decode<Outbox>(Outbox.schema, Outbox, accountInfo.data)
       .documents_counter;
   }
 }
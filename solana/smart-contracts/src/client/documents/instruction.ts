@@ -1,4 +1,5 @@
 import {Schema, SolanaBorsh} from '../solanaBorsh';
+import {DocumentPayload, schema as stateSchema} from './state';
 
 export enum DocumentsInstruction {
   CreateReceiverAccount = 'CreateReceiverAccount',
@@ -38,13 +39,16 @@ export class InstructionData extends SolanaBorsh {
         },
       ],
     ]),
-    [DocumentsInstruction.SendDocument]: new Map([
+    [DocumentsInstruction.SendDocument]: new Map<any, any>([
+      ...Array.from(stateSchema.entries()),
       [
         InstructionData,
         {
           kind: 'struct',
           fields: [
-            ['data', ['u8']],
+            ['data', DocumentPayload],
+            ['in_reply_to', {kind: 'option', type: 'u32'}],
+            ['encryption_key_version', 'u32'],
           ],
         },
       ],
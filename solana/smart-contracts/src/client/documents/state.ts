@@ -2,29 +2,59 @@ import {Schema} from 'borsh';
 import {SolanaBorsh} from '../solanaBorsh';
 import {PublicKey} from '@solana/web3.js';
 import BN from 'bn.js';
+import {createHash} from 'crypto';
+
+// Hash algorithm of the off-chain document digest
+export enum HashAlgorithm {
+  Sha256,
+  Sha512,
+  Keccak256,
+  Blake2b256,
+}
+
+// Encryption scheme of the off-chain document
+export enum EncryptionScheme {
+  None,
+  X25519XSalsa20Poly1305,
+  X25519Aes256Gcm,
+}
+
+// Encryption key of the receiver
+export type EncryptionKeyType = Omit<EncryptionKey, 'assign' | 'encode'>;
+
+export class EncryptionKey extends SolanaBorsh {
+  scheme = EncryptionScheme.None;
+  public_key: Uint8Array = new Uint8Array(32);
+  version = 0;
+
+  constructor(properties: EncryptionKeyType | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
 
 // Receiver account
 export type ReceiverType = Omit<Receiver, 'assign' | 'encode'>;
 
 export class Receiver extends SolanaBorsh {
   documents_counter = 0;
+  access_policy = 0;
+  version = 0;
+  authority: Uint8Array = new Uint8Array(32);
+  postage: BN = new BN(0);
+  encryption_key: EncryptionKey = new EncryptionKey();
 
   static ACCOUNT_ADDRESS_SEED = 'receiver';
 
-  static schema: Schema = new Map([
-    [
-      Receiver,
-      {
-        kind: 'struct',
-        fields: [
-          ['documents_counter', 'u32'],
-        ],
-      },
-    ],
-  ]);
+  static get schema(): Schema {
+    return schema;
+  }
 
   constructor(properties: ReceiverType | undefined = undefined) {
-    super(Receiver.schema);
+    super(schema);
 
     if (properties) {
       this.assign(properties);
@@ -47,40 +77,161 @@ export class Receiver extends SolanaBorsh {
   }
 }
 
+// Payload describing the off-chain document
+export type DocumentPayloadType = Omit<DocumentPayload, 'assign' | 'encode'>;
+
+export class DocumentPayload extends SolanaBorsh {
+  static VERSION = 2;
+
+  version = DocumentPayload.VERSION;
+  uri = '';
+  hash_algorithm = HashAlgorithm.Sha256;
+  digest: Uint8Array = new Uint8Array(0);
+  mime_type = '';
+  size: BN = new BN(0);
+  encryption_scheme = EncryptionScheme.None;
+  expires_at: BN | null = null;
+
+  constructor(properties: DocumentPayloadType | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
+
+// Reference to the payload shared by the receivers of a batch
+export type ContentReferenceType = Omit<ContentReference, 'assign' | 'encode'>;
+
+export class ContentReference extends SolanaBorsh {
+  address: Uint8Array = new Uint8Array(32);
+  expires_at: BN | null = null;
+
+  constructor(properties: ContentReferenceType | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
+
+// Data of a document, stored in the document account or shared by the receivers of a batch
+export class DocumentData extends SolanaBorsh {
+  enum: 'payload' | 'content' = 'payload';
+  payload: DocumentPayload | undefined;
+  content: ContentReference | undefined;
+
+  constructor(properties: {payload?: DocumentPayload, content?: ContentReference}) {
+    super(schema);
+
+    this.enum = properties.content ? 'content' : 'payload';
+    this.assign(properties);
+  }
+}
+
+// Receipt the receiver issues for a document
+export class Receipt extends SolanaBorsh {
+  status = 0;
+  reason_hash: Uint8Array | null = null;
+  issued_at: BN = new BN(0);
+  rent: BN = new BN(0);
+
+  constructor(properties: Partial<Receipt> | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
+
+// Provenance of a forwarded document
+export class ForwardedFrom extends SolanaBorsh {
+  receiver: Uint8Array = new Uint8Array(32);
+  document_index = 0;
+  sender: Uint8Array = new Uint8Array(32);
+  sent_at: BN = new BN(0);
+
+  constructor(properties: Partial<ForwardedFrom> | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
+
+// Inbox state of a document, written only by the receiver
+export class ReceiverMetadata extends SolanaBorsh {
+  archived = 0;
+  pinned = 0;
+  labels: BN = new BN(0);
+
+  constructor(properties: Partial<ReceiverMetadata> | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+}
+
 // Document account
 export type DocumentType = Omit<Document, 'assign' | 'encode'>;
 
 export class Document extends SolanaBorsh {
-  sender: PublicKey | undefined;
-  data: Uint8Array | undefined;
+  version: number | undefined;
+  sender: Uint8Array | undefined;
+  data: DocumentData | undefined;
   sent_at: BN | undefined;
   opened_at: BN | undefined;
+  revoked_at: BN | undefined;
+  receipt: Receipt | null | undefined;
+  in_reply_to: number | null | undefined;
+  id: Uint8Array | undefined;
+  encryption_key_version: number | undefined;
+  forwarded_from: ForwardedFrom | null | undefined;
+  receiver_metadata: ReceiverMetadata | undefined;
+  amended_at: BN | undefined;
+  amendments_count: number | undefined;
+  funder: Uint8Array | undefined;
+  sent_document_index: number | undefined;
 
   static ACCOUNT_ADDRESS_SEED = 'document';
+  static VERSION = 1;
 
-  static schema: Schema = new Map([
-    [
-      Document,
-      {
-        kind: 'struct',
-        fields: [
-          ['sender', [32]],
-          ['data', ['u8']],
-          ['sent_at', 'u64'],
-          ['opened_at', 'u64'],
-        ],
-      },
-    ],
-  ]);
+  static get schema(): Schema {
+    return schema;
+  }
 
   constructor(properties: DocumentType) {
-    super(Document.schema);
+    super(schema);
 
     if (properties) {
       this.assign(properties);
     }
   }
 
+  // Global ID of the document, the hash of its sender, receiver, index and data
+  static computeId(
+    senderAddress: PublicKey,
+    receiverAddress: PublicKey,
+    documentIndex: number,
+    data: DocumentData,
+  ): Buffer {
+    const index = Buffer.alloc(4);
+    index.writeUInt32LE(documentIndex);
+
+    return createHash('sha256')
+      .update(senderAddress.toBuffer())
+      .update(receiverAddress.toBuffer())
+      .update(index)
+      .update(data.encode())
+      .digest();
+  }
+
   static async findPdaAddress(
     documentIndex: number,
     receiverAddress: PublicKey,
@@ -97,3 +248,235 @@ export class Document extends SolanaBorsh {
     return publicKeyNonce[0];
   }
 }
+
+// Document locator account, references the document with the global ID
+export class DocumentLocator {
+  static ACCOUNT_ADDRESS_SEED = 'locator';
+
+  static async findPdaAddress(
+    documentId: Buffer,
+    programId: PublicKey,
+  ): Promise<PublicKey> {
+    const publicKeyNonce = await PublicKey.findProgramAddress(
+      [
+        documentId,
+        Buffer.from(DocumentLocator.ACCOUNT_ADDRESS_SEED),
+      ],
+      programId,
+    );
+
+    return publicKeyNonce[0];
+  }
+}
+
+// Sender outbox account
+export class Outbox extends SolanaBorsh {
+  documents_counter = 0;
+
+  static ACCOUNT_ADDRESS_SEED = 'outbox';
+
+  static get schema(): Schema {
+    return schema;
+  }
+
+  constructor(properties: Partial<Outbox> | undefined = undefined) {
+    super(schema);
+
+    if (properties) {
+      this.assign(properties);
+    }
+  }
+
+  static async findPdaAddress(
+    senderAddress: PublicKey,
+    programId: PublicKey,
+  ): Promise<PublicKey> {
+    const publicKeyNonce = await PublicKey.findProgramAddress(
+      [
+        senderAddress.toBuffer(),
+        Buffer.from(Outbox.ACCOUNT_ADDRESS_SEED),
+      ],
+      programId,
+    );
+
+    return publicKeyNonce[0];
+  }
+}
+
+// Sent document account, references the document of the receiver
+export class SentDocument {
+  static ACCOUNT_ADDRESS_SEED = 'sent';
+
+  static async findPdaAddress(
+    sentDocumentIndex: number,
+    senderAddress: PublicKey,
+    programId: PublicKey,
+  ): Promise<PublicKey> {
+    const publicKeyNonce = await PublicKey.findProgramAddress(
+      [
+        senderAddress.toBuffer(),
+        Buffer.from(sentDocumentIndex.toString() + SentDocument.ACCOUNT_ADDRESS_SEED),
+      ],
+      programId,
+    );
+
+    return publicKeyNonce[0];
+  }
+}
+
+// Sender access account of the receiver
+export class SenderAccessEntry {
+  static ACCOUNT_ADDRESS_SEED = 'access';
+
+  static async findPdaAddress(
+    receiverAddress: PublicKey,
+    senderAddress: PublicKey,
+    programId: PublicKey,
+  ): Promise<PublicKey> {
+    const publicKeyNonce = await PublicKey.findProgramAddress(
+      [
+        receiverAddress.toBuffer(),
+        senderAddress.toBuffer(),
+        Buffer.from(SenderAccessEntry.ACCOUNT_ADDRESS_SEED),
+      ],
+      programId,
+    );
+
+    return publicKeyNonce[0];
+  }
+}
+
+// Borsh layout of the program accounts, mirrors `state.rs` of the program. Enums without
+// data are encoded as their u8 index and booleans as u8.
+export const schema: Schema = new Map<any, any>([
+  [
+    EncryptionKey,
+    {
+      kind: 'struct',
+      fields: [
+        ['scheme', 'u8'],
+        ['public_key', [32]],
+        ['version', 'u32'],
+      ],
+    },
+  ],
+  [
+    Receiver,
+    {
+      kind: 'struct',
+      fields: [
+        ['documents_counter', 'u32'],
+        ['access_policy', 'u8'],
+        ['version', 'u8'],
+        ['authority', [32]],
+        ['postage', 'u64'],
+        ['encryption_key', EncryptionKey],
+      ],
+    },
+  ],
+  [
+    DocumentPayload,
+    {
+      kind: 'struct',
+      fields: [
+        ['version', 'u8'],
+        ['uri', 'string'],
+        ['hash_algorithm', 'u8'],
+        ['digest', ['u8']],
+        ['mime_type', 'string'],
+        ['size', 'u64'],
+        ['encryption_scheme', 'u8'],
+        ['expires_at', {kind: 'option', type: 'u64'}],
+      ],
+    },
+  ],
+  [
+    ContentReference,
+    {
+      kind: 'struct',
+      fields: [
+        ['address', [32]],
+        ['expires_at', {kind: 'option', type: 'u64'}],
+      ],
+    },
+  ],
+  [
+    DocumentData,
+    {
+      kind: 'enum',
+      field: 'enum',
+      values: [
+        ['payload', DocumentPayload],
+        ['content', ContentReference],
+      ],
+    },
+  ],
+  [
+    Receipt,
+    {
+      kind: 'struct',
+      fields: [
+        ['status', 'u8'],
+        ['reason_hash', {kind: 'option', type: [32]}],
+        ['issued_at', 'u64'],
+        ['rent', 'u64'],
+      ],
+    },
+  ],
+  [
+    ForwardedFrom,
+    {
+      kind: 'struct',
+      fields: [
+        ['receiver', [32]],
+        ['document_index', 'u32'],
+        ['sender', [32]],
+        ['sent_at', 'u64'],
+      ],
+    },
+  ],
+  [
+    ReceiverMetadata,
+    {
+      kind: 'struct',
+      fields: [
+        ['archived', 'u8'],
+        ['pinned', 'u8'],
+        ['labels', 'u64'],
+      ],
+    },
+  ],
+  [
+    Document,
+    {
+      kind: 'struct',
+      fields: [
+        ['version', 'u8'],
+        ['sender', [32]],
+        ['data', DocumentData],
+        ['sent_at', 'u64'],
+        ['opened_at', 'u64'],
+        ['revoked_at', 'u64'],
+        ['receipt', {kind: 'option', type: Receipt}],
+        ['in_reply_to', {kind: 'option', type: 'u32'}],
+        ['id', [32]],
+        ['encryption_key_version', 'u32'],
+        ['forwarded_from', {kind: 'option', type: ForwardedFrom}],
+        ['receiver_metadata', ReceiverMetadata],
+        ['amended_at', 'u64'],
+        ['amendments_count', 'u32'],
+        ['funder', [32]],
+        ['sent_document_index', 'u32'],
+      ],
+    },
+  ],
+  [
+    Outbox,
+    {
+      kind: 'struct',
+      fields: [
+        ['documents_counter', 'u32'],
+      ],
+    },
+  ],
+]);
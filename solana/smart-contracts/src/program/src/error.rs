@@ -1,14 +1,142 @@
 use {
-    solana_program::{decode_error::DecodeError, program_error::ProgramError},
+    num_derive::FromPrimitive,
+    solana_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
     thiserror::Error,
 };
 
 /// Errors that may be returned by the program
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum DocumentsError {
     /// Incorrect account address derivation
     #[error("Incorrect account address derivation")]
     AddressDerivationMismatch,
+    /// Required signature is missing
+    #[error("Required signature is missing")]
+    MissingSignature,
+    /// Account is not owned by the program
+    #[error("Account is not owned by the program")]
+    IncorrectOwner,
+    /// Invalid sysvar account
+    #[error("Invalid sysvar account")]
+    InvalidSysvar,
+    /// Receiver account is not initialized
+    #[error("Receiver account is not initialized")]
+    ReceiverNotInitialized,
+    /// Receiver account is already initialized
+    #[error("Receiver account is already initialized")]
+    ReceiverAlreadyInitialized,
+    /// Receiver account is not rent exempt
+    #[error("Receiver account is not rent exempt")]
+    NotRentExempt,
+    /// Document account does not exist
+    #[error("Document account does not exist")]
+    DocumentNotFound,
+    /// Document account already exists
+    #[error("Document account already exists")]
+    DocumentAlreadyExists,
+    /// Sent document account already exists
+    #[error("Sent document account already exists")]
+    SentDocumentAlreadyExists,
+    /// Document payload is not well-formed
+    #[error("Document payload is not well-formed")]
+    InvalidPayload,
+    /// Document payload exceeds the maximum length
+    #[error("Document payload exceeds the maximum length")]
+    PayloadTooLarge,
+    /// Document was already opened
+    #[error("Document was already opened")]
+    AlreadyOpened,
+    /// Document was not opened yet
+    #[error("Document was not opened yet")]
+    NotOpened,
+    /// Signer is neither the receiver nor the sender of the document
+    #[error("Signer is neither the receiver nor the sender of the document")]
+    NotReceiverOrSender,
+    /// Nonce does not match the sender nonce account
+    #[error("Nonce does not match the sender nonce account")]
+    InvalidNonce,
+    /// Ed25519 program instruction is missing
+    #[error("Ed25519 program instruction is missing")]
+    MissingEd25519Instruction,
+    /// Ed25519 program instruction is not supported
+    #[error("Ed25519 program instruction is not supported")]
+    InvalidEd25519Instruction,
+    /// Signature does not match the signer or the message
+    #[error("Signature does not match the signer or the message")]
+    InvalidSignature,
+    /// Arithmetic operation overflowed
+    #[error("Arithmetic operation overflowed")]
+    Overflow,
+    /// Sender is not allowed to send documents to the receiver
+    #[error("Sender is not allowed to send documents to the receiver")]
+    SenderNotAllowed,
+    /// Sender access account does not exist
+    #[error("Sender access account does not exist")]
+    SenderAccessNotFound,
+    /// Signer is not the authority of the receiver
+    #[error("Signer is not the authority of the receiver")]
+    InvalidAuthority,
+    /// Delegate account does not exist
+    #[error("Delegate account does not exist")]
+    DelegateNotFound,
+    /// Delegate expired
+    #[error("Delegate expired")]
+    DelegateExpired,
+    /// Signer is not the sender of the document
+    #[error("Signer is not the sender of the document")]
+    NotSender,
+    /// Document was recalled by the sender
+    #[error("Document was recalled by the sender")]
+    DocumentRevoked,
+    /// Document expired
+    #[error("Document expired")]
+    DocumentExpired,
+    /// Receiver accounts of the batch are missing or incomplete
+    #[error("Receiver accounts of the batch are missing or incomplete")]
+    InvalidReceiverAccounts,
+    /// Content account already exists
+    #[error("Content account already exists")]
+    ContentAlreadyExists,
+    /// Receiver already acknowledged or rejected the document
+    #[error("Receiver already acknowledged or rejected the document")]
+    ReceiptAlreadyIssued,
+    /// Encryption key must have an encryption scheme
+    #[error("Encryption key must have an encryption scheme")]
+    InvalidEncryptionKey,
+    /// Wrapped key is empty or too long
+    #[error("Wrapped key is empty or too long")]
+    InvalidWrappedKey,
+    /// Key envelope already exists
+    #[error("Key envelope already exists")]
+    KeyEnvelopeAlreadyExists,
+    /// Key envelope not found
+    #[error("Key envelope not found")]
+    KeyEnvelopeNotFound,
+    /// Account is not the funder of the document
+    #[error("Account is not the funder of the document")]
+    NotFunder,
+    /// Document was neither recalled nor expired
+    #[error("Document was neither recalled nor expired")]
+    DocumentNotRevokedOrExpired,
+    /// Account is not the party that granted the access
+    #[error("Account is not the party that granted the access")]
+    NotGrantor,
+    /// Document was not closed yet
+    #[error("Document was not closed yet")]
+    DocumentNotClosed,
+    /// Encryption key version does not match the current key of the receiver
+    #[error("Encryption key version does not match the current key of the receiver")]
+    EncryptionKeyVersionMismatch,
+    /// Document account has an unsupported layout version
+    #[error("Document account has an unsupported layout version")]
+    UnsupportedDocumentVersion,
+    /// Document refers to the content shared by a batch
+    #[error("Document refers to the content shared by a batch")]
+    SharedContentNotAmendable,
 }
 
 impl From<DocumentsError> for ProgramError {
@@ -21,4 +149,10 @@ impl<T> DecodeError<T> for DocumentsError {
     fn type_of() -> &'static str {
         "Documents Error"
     }
-}
\ No newline at end of file
+}
+
+impl PrintProgramError for DocumentsError {
+    fn print<E>(&self) {
+        msg!("Error: {}", self);
+    }
+}
@@ -1,6 +1,7 @@
 use {
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
+        clock::UnixTimestamp,
         instruction::{
             AccountMeta,
             Instruction,
@@ -14,12 +15,22 @@ use {
         state::Receiver,
     },
 };
-use crate::state::Document;
+use crate::state::{
+    AccessPolicy, Content, Delegate, Document, DocumentData, DocumentLocator, DocumentPayload, EncryptionScheme, KeyEnvelope,
+    Outbox, ReceiverMetadata, SenderAccess, SenderAccessEntry, SenderNonce, SentDocument,
+};
+
+/// Domain separator of the pre-signed document message
+pub const PRE_SIGNED_DOCUMENT_DOMAIN: u8 = 0;
+/// Number of accounts of every receiver of SendDocumentToMany
+pub const SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS: usize = 6;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub enum DocumentsInstruction {
     /// Create a new receiver account
     ///
+    /// The wallet of the receiver is the initial authority of the account.
+    ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` Sender account/Funder account (must be a system account)
@@ -31,18 +42,419 @@ pub enum DocumentsInstruction {
 
     /// Create a new document account
     ///
+    /// The funding account pays the postage of the receiver to the receiver wallet.
+    /// The document locator references the document by its global ID, see `Document::compute_id`.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Funding account (must be a system account)
+    /// 1. `[signer]` Wallet address of the document sender
+    /// 2. `[writable]` PDA address of the receiver of the document
+    /// 3. `[writable]` PDA address of the document
+    /// 4. `[writable]` PDA address of the document locator
+    /// 5. `[writable]` PDA address of the sender outbox
+    /// 6. `[writable]` PDA address of the sent document
+    /// 7. `[]` PDA address of the sender access to the receiver
+    /// 8. `[writable]` Wallet address of the document receiver
+    /// 9. `[]` Rent sysvar
+    /// 10. `[]` Clock sysvar
+    /// 11. `[]` System program
+    /// 12. `[]` (Optional) PDA address of the replied document of the sender, when `in_reply_to` is given
+    SendDocument {
+        /// Payload describing the off-chain document
+        data: DocumentPayload,
+        /// Index of the document of the sender this document replies to
+        in_reply_to: Option<u32>,
+        /// Version of the receiver encryption key the document is encrypted to, must match the
+        /// current encryption key of the receiver
+        encryption_key_version: u32,
+    },
+
+    /// Mark the document as opened
+    ///
+    /// The document can be opened by its receiver or an active delegate of the receiver.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Wallet address of the document receiver or of the delegate
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[]` Clock sysvar
+    /// 3. `[]` (Optional) Wallet address of the document receiver, when opened by the delegate
+    /// 4. `[]` (Optional) PDA address of the delegate, when opened by the delegate
+    MarkDocumentOpened {
+        /// Index of the receiver's document
+        document_index: u32,
+    },
+
+    /// Create a new document account on behalf of the sender, who signed the
+    /// `pre_signed_document_message` in the preceding Ed25519 program instruction
+    ///
+    /// The relayer pays the postage of the receiver to the receiver wallet.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Relayer account/Funding account (must be a system account)
+    /// 1. `[]` Wallet address of the document sender
+    /// 2. `[writable]` PDA address of the sender nonce
+    /// 3. `[writable]` PDA address of the receiver of the document
+    /// 4. `[writable]` PDA address of the document
+    /// 5. `[writable]` PDA address of the document locator
+    /// 6. `[writable]` PDA address of the sender outbox
+    /// 7. `[writable]` PDA address of the sent document
+    /// 8. `[]` PDA address of the sender access to the receiver
+    /// 9. `[writable]` Wallet address of the document receiver
+    /// 10. `[]` Rent sysvar
+    /// 11. `[]` Clock sysvar
+    /// 12. `[]` Instructions sysvar
+    /// 13. `[]` System program
+    /// 14. `[]` (Optional) PDA address of the replied document of the sender, when `in_reply_to` is given
+    SendPreSignedDocument {
+        /// Payload describing the off-chain document
+        data: DocumentPayload,
+        /// Nonce of the sender, must match the sender nonce account
+        nonce: u64,
+        /// Index of the document of the sender this document replies to
+        in_reply_to: Option<u32>,
+        /// Version of the receiver encryption key the document is encrypted to, must match the
+        /// current encryption key of the receiver
+        encryption_key_version: u32,
+    },
+
+    /// Close a document account and return its rent to the funder of the document
+    ///
+    /// The receiver can close the document once it opened it, the sender recalled it or it
+    /// expired. The sender can only close a document it recalled or that expired unopened.
+    /// The sent document account referencing the document is closed with it. A batch document
+    /// releases its content account, which is closed with the last document referencing it. The documents
+    /// counters of the receiver and of the outbox are not decremented, so the indexes of a
+    /// closed document are never reused and their PDA addresses stay empty.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Wallet address of the document receiver or sender
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[writable]` PDA address of the document locator
+    /// 3. `[writable]` Wallet address of the document receiver, receiving the rent of the receipt
+    /// 4. `[writable]` Funder of the document receiving the reclaimed lamports
+    /// 5. `[writable]` PDA address of the sent document of the sender
+    /// 6. `[]` Clock sysvar
+    /// 7. `[writable]` (Optional) PDA address of the content, when the document is a batch document
+    /// 8. `[writable]` (Optional) Funder of the content, when the document is a batch document
+    CloseDocument {
+        /// Index of the receiver's document
+        document_index: u32,
+    },
+
+    /// Update the settings of the receiver account
+    ///
+    /// Receiver accounts of older versions are migrated and the authority pays the rent
+    /// of the extra space.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Authority of the receiver account
+    /// 1. `[writable]` PDA address of the receiver
+    /// 2. `[]` Wallet address of the document receiver
+    /// 3. `[]` Rent sysvar
+    /// 4. `[]` System program
+    UpdateReceiver {
+        /// New authority of the receiver account, none to keep the current one
+        authority: Option<Pubkey>,
+        /// New access policy of the receiver, none to keep the current one
+        access_policy: Option<AccessPolicy>,
+        /// New postage of the receiver in lamports, none to keep the current one
+        postage: Option<u64>,
+    },
+
+    /// Allow or block the sender, or remove the sender from the lists of the receiver
+    ///
+    /// The authority pays the rent of a new sender access account and receives it back
+    /// once the access is removed.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Authority of the receiver account
+    /// 1. `[]` PDA address of the receiver
+    /// 2. `[writable]` PDA address of the sender access to the receiver
+    /// 3. `[]` Wallet address of the document receiver
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` System program
+    SetSenderAccess {
+        /// Wallet address of the document sender
+        sender: Pubkey,
+        /// Access of the sender, none to remove the sender from the lists
+        access: Option<SenderAccess>,
+    },
+
+    /// Migrate the receiver account of an older version to the current version
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Funding account of the extra space (must be a system account)
+    /// 1. `[writable]` PDA address of the receiver
+    /// 2. `[]` Wallet address of the document receiver
+    /// 3. `[]` Rent sysvar
+    /// 4. `[]` System program
+    MigrateReceiver,
+
+    /// Authorize the delegate to open documents on behalf of the receiver, or update
+    /// the expiry of an existing delegate
+    ///
+    /// The authority pays the rent of a new delegate account.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Authority of the receiver account
+    /// 1. `[]` PDA address of the receiver
+    /// 2. `[writable]` PDA address of the delegate
+    /// 3. `[]` Wallet address of the document receiver
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` System program
+    AddDelegate {
+        /// Wallet address of the delegate
+        delegate: Pubkey,
+        /// Timestamp from which the delegate can no longer act, none if it never expires
+        expires_at: Option<UnixTimestamp>,
+    },
+
+    /// Revoke the delegate and return the rent of its account to the authority
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Authority of the receiver account
+    /// 1. `[]` PDA address of the receiver
+    /// 2. `[writable]` PDA address of the delegate
+    /// 3. `[]` Wallet address of the document receiver
+    RemoveDelegate {
+        /// Wallet address of the delegate
+        delegate: Pubkey,
+    },
+
+    /// Recall an unopened document, so the receiver can no longer open it
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Wallet address of the document sender
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[]` Wallet address of the document receiver
+    /// 3. `[]` Clock sysvar
+    RecallDocument {
+        /// Index of the receiver's document
+        document_index: u32,
+    },
+
+    /// Send the document to several receivers, storing the payload once in a content account
+    /// of the sender and creating a document account referencing it for every receiver
+    ///
+    /// The content is indexed by the sent document index of the first document of the batch.
+    /// The funding account pays the postage of every receiver.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Funding account (must be a system account)
+    /// 1. `[signer]` Wallet address of the document sender
+    /// 2. `[writable]` PDA address of the sender outbox
+    /// 3. `[writable]` PDA address of the content
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` Clock sysvar
+    /// 6. `[]` System program
+    /// 7. `[]` (Optional) PDA address of the replied document of the sender, when `in_reply_to` is given
+    ///
+    /// Followed by the accounts of every receiver:
+    ///
+    /// 0. `[writable]` PDA address of the receiver of the document
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[writable]` PDA address of the document locator
+    /// 3. `[writable]` PDA address of the sent document
+    /// 4. `[]` PDA address of the sender access to the receiver
+    /// 5. `[writable]` Wallet address of the document receiver
+    SendDocumentToMany {
+        /// Payload describing the off-chain document
+        data: DocumentPayload,
+        /// Index of the document of the sender this document replies to
+        in_reply_to: Option<u32>,
+        /// Versions of the encryption keys of the receivers the document is encrypted to, in the
+        /// order of the receivers, each must match the current encryption key of its receiver
+        encryption_key_versions: Vec<u32>,
+    },
+
+    /// Acknowledge the document, opening it if it is not opened yet
+    ///
+    /// The receiver pays the rent of the receipt stored in the document account, which is
+    /// returned to it when the document is closed.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Wallet address of the document receiver
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[]` Rent sysvar
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` System program
+    AcknowledgeDocument {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// Hash of the off-chain reason of the receiver
+        reason_hash: Option<[u8; 32]>,
+    },
+
+    /// Reject the document, opening it if it is not opened yet
+    ///
+    /// The receiver pays the rent of the receipt stored in the document account, which is
+    /// returned to it when the document is closed.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Wallet address of the document receiver
+    /// 1. `[writable]` PDA address of the document
+    /// 2. `[]` Rent sysvar
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` System program
+    RejectDocument {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// Hash of the off-chain reason of the receiver
+        reason_hash: Option<[u8; 32]>,
+    },
+
+    /// Publish or rotate the encryption key of the receiver, incrementing its version
+    ///
+    /// Documents record the key version of the receiver at the time they were sent.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Authority of the receiver account
+    /// 1. `[writable]` PDA address of the receiver
+    /// 2. `[]` Wallet address of the document receiver
+    /// 3. `[]` Rent sysvar
+    /// 4. `[]` System program
+    SetEncryptionKey {
+        /// Encryption scheme of the key
+        scheme: EncryptionScheme,
+        /// X25519 public key
+        public_key: [u8; 32],
+    },
+
+    /// Grant the party access to the document by storing the symmetric key of the document
+    /// wrapped to the encryption key of the grantee
+    ///
+    /// The receiver or the sender of the document grants the access and pays the rent of
+    /// the key envelope.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Wallet address of the document receiver or sender
+    /// 1. `[]` PDA address of the document
+    /// 2. `[writable]` PDA address of the key envelope of the grantee
+    /// 3. `[]` Wallet address of the document receiver
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` System program
+    GrantDocumentAccess {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// Wallet address of the party granted the access
+        grantee: Pubkey,
+        /// Symmetric key of the document wrapped to the encryption key of the grantee
+        wrapped_key: Vec<u8>,
+    },
+
+    /// Revoke the access of the party to the document, closing its key envelope and
+    /// returning the rent to the party that granted the access
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Wallet address of the document receiver or sender
+    /// 1. `[]` PDA address of the document
+    /// 2. `[writable]` PDA address of the key envelope of the grantee
+    /// 3. `[]` Wallet address of the document receiver
+    /// 4. `[writable]` Wallet address of the party that granted the access
+    RevokeDocumentAccess {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// Wallet address of the party whose access is revoked
+        grantee: Pubkey,
+    },
+
+    /// Forward the document to another receiver, copying its data
+    ///
+    /// The forwarder is the sender of the new document, which records the receiver, index,
+    /// sender and send time of the original document. A batch document is forwarded as a
+    /// reference to the same content account.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Funding account (must be a system account)
+    /// 1. `[signer]` Wallet address of the receiver of the original document, the forwarder
+    /// 2. `[]` PDA address of the original document
+    /// 3. `[writable]` PDA address of the receiver of the forwarded document
+    /// 4. `[writable]` PDA address of the forwarded document
+    /// 5. `[writable]` PDA address of the document locator
+    /// 6. `[writable]` PDA address of the forwarder outbox
+    /// 7. `[writable]` PDA address of the sent document
+    /// 8. `[]` PDA address of the forwarder access to the receiver
+    /// 9. `[writable]` Wallet address of the receiver of the forwarded document
+    /// 10. `[]` Rent sysvar
+    /// 11. `[]` Clock sysvar
+    /// 12. `[]` System program
+    /// 13. `[writable]` (Optional) PDA address of the content, when the original document is a batch document
+    ForwardDocument {
+        /// Index of the forwarder's original document
+        document_index: u32,
+        /// Version of the encryption key of the receiver of the forwarded document, must match the
+        /// current encryption key of the receiver
+        encryption_key_version: u32,
+    },
+
+    /// Set the archived flag, pinned flag and labels the receiver organizes the document by
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Wallet address of the document receiver
+    /// 1. `[writable]` PDA address of the document
+    SetReceiverMetadata {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// New inbox state of the document
+        metadata: ReceiverMetadata,
+    },
+
+    /// Replace the payload of an unopened document, resizing the document account
+    ///
+    /// The funder of the document tops up the rent when the document grows and gets back the
+    /// released rent when it shrinks. Documents of a batch share their content and can not be
+    /// amended. The global ID of the document does not change.
+    ///
     /// Accounts expected:
     ///
-    /// 0. `[signer]` Sender account/Funding account (must be a system account)
-    /// 1. `[writable]` PDA address of the receiver of the document
+    /// 0. `[signer, writable]` Funder of the document (must be a system account)
+    /// 1. `[signer]` Wallet address of the document sender
     /// 2. `[writable]` PDA address of the document
     /// 3. `[]` Wallet address of the document receiver
     /// 4. `[]` Rent sysvar
     /// 5. `[]` Clock sysvar
     /// 6. `[]` System program
-    SendDocument {
-        /// Link of the meta file and checksum
-        data: Vec<u8>,
+    AmendDocument {
+        /// Index of the receiver's document
+        document_index: u32,
+        /// New payload describing the off-chain document
+        data: DocumentPayload,
+    },
+
+    /// Close the key envelope of a closed document, returning the rent to the party that
+    /// granted the access
+    ///
+    /// Key envelopes are not closed with their document. The document is closed once its
+    /// document locator no longer exists.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Wallet address of the party that granted the access
+    /// 1. `[writable]` PDA address of the key envelope of the grantee
+    /// 2. `[]` PDA address of the document locator
+    CloseKeyEnvelope {
+        /// Global ID of the closed document
+        document_id: [u8; 32],
+        /// Wallet address of the party granted the access
+        grantee: Pubkey,
     },
 }
 
@@ -52,7 +464,7 @@ pub fn create_receiver_account(
     wallet_address: &Pubkey,
 ) -> Instruction {
     let receiver_pda_address = Receiver::find_pda_address(
-        &wallet_address,
+        wallet_address,
         &id(),
     );
 
@@ -69,37 +481,924 @@ pub fn create_receiver_account(
     )
 }
 
-/// Creates SendDocument instruction
+/// Creates SendDocument instruction where the funder is also the sender
 pub fn send_document(
     funder_address: &Pubkey,
     wallet_address: &Pubkey,
     document_index: u32,
-    document_data: Vec<u8>,
+    sent_document_index: u32,
+    document_data: DocumentPayload,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
+) -> Instruction {
+    send_funded_document(
+        funder_address,
+        funder_address,
+        wallet_address,
+        document_index,
+        sent_document_index,
+        document_data,
+        in_reply_to,
+        encryption_key_version,
+    )
+}
+
+/// Creates SendDocument instruction where the funder pays for the document of the sender
+#[allow(clippy::too_many_arguments)]
+pub fn send_funded_document(
+    funder_address: &Pubkey,
+    sender_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    sent_document_index: u32,
+    document_data: DocumentPayload,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
 ) -> Instruction {
     let receiver_pda_address = Receiver::find_pda_address(
-        &wallet_address,
+        wallet_address,
         &id(),
     );
 
     let document_pda_address = Document::find_pda_address(
         document_index,
-        &wallet_address,
+        wallet_address,
+        &id(),
+    );
+
+    let outbox_pda_address = Outbox::find_pda_address(
+        sender_address,
+        &id(),
+    );
+
+    let sent_document_pda_address = SentDocument::find_pda_address(
+        sent_document_index,
+        sender_address,
+        &id(),
+    );
+
+    let sender_access_pda_address = SenderAccessEntry::find_pda_address(
+        wallet_address,
+        sender_address,
         &id(),
     );
 
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        &Document::compute_id(
+            sender_address,
+            wallet_address,
+            document_index,
+            &DocumentData::Payload(document_data.clone()),
+        ),
+        &id(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta::new(*funder_address, true),
+        AccountMeta::new_readonly(*sender_address, true),
+        AccountMeta::new(receiver_pda_address, false),
+        AccountMeta::new(document_pda_address, false),
+        AccountMeta::new(document_locator_pda_address, false),
+        AccountMeta::new(outbox_pda_address, false),
+        AccountMeta::new(sent_document_pda_address, false),
+        AccountMeta::new_readonly(sender_access_pda_address, false),
+        AccountMeta::new(*wallet_address, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if let Some(replied_document_index) = in_reply_to {
+        accounts.push(AccountMeta::new_readonly(
+            Document::find_pda_address(replied_document_index, sender_address, &id()),
+            false,
+        ));
+    }
+
     Instruction::new_with_borsh(
         id(),
         &DocumentsInstruction::SendDocument {
             data: document_data,
+            in_reply_to,
+            encryption_key_version,
+        },
+        accounts,
+    )
+}
+
+/// Creates MarkDocumentOpened instruction
+pub fn mark_document_opened(
+    wallet_address: &Pubkey,
+    document_index: u32,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::MarkDocumentOpened {
+            document_index,
         },
         vec![
-            AccountMeta::new(*funder_address, true),
-            AccountMeta::new(receiver_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, true),
             AccountMeta::new(document_pda_address, false),
-            AccountMeta::new(*wallet_address, false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
             AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
         ],
     )
-}
\ No newline at end of file
+}
+
+/// Creates MarkDocumentOpened instruction signed by the delegate of the receiver
+pub fn mark_document_opened_by_delegate(
+    delegate_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let delegate_pda_address = Delegate::find_pda_address(
+        wallet_address,
+        delegate_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::MarkDocumentOpened {
+            document_index,
+        },
+        vec![
+            AccountMeta::new_readonly(*delegate_address, true),
+            AccountMeta::new(document_pda_address, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(delegate_pda_address, false),
+        ],
+    )
+}
+
+/// Creates SendPreSignedDocument instruction
+#[allow(clippy::too_many_arguments)]
+pub fn send_pre_signed_document(
+    relayer_address: &Pubkey,
+    sender_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    sent_document_index: u32,
+    document_data: DocumentPayload,
+    nonce: u64,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
+) -> Instruction {
+    let sender_nonce_pda_address = SenderNonce::find_pda_address(
+        sender_address,
+        &id(),
+    );
+
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let outbox_pda_address = Outbox::find_pda_address(
+        sender_address,
+        &id(),
+    );
+
+    let sent_document_pda_address = SentDocument::find_pda_address(
+        sent_document_index,
+        sender_address,
+        &id(),
+    );
+
+    let sender_access_pda_address = SenderAccessEntry::find_pda_address(
+        wallet_address,
+        sender_address,
+        &id(),
+    );
+
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        &Document::compute_id(
+            sender_address,
+            wallet_address,
+            document_index,
+            &DocumentData::Payload(document_data.clone()),
+        ),
+        &id(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta::new(*relayer_address, true),
+        AccountMeta::new_readonly(*sender_address, false),
+        AccountMeta::new(sender_nonce_pda_address, false),
+        AccountMeta::new(receiver_pda_address, false),
+        AccountMeta::new(document_pda_address, false),
+        AccountMeta::new(document_locator_pda_address, false),
+        AccountMeta::new(outbox_pda_address, false),
+        AccountMeta::new(sent_document_pda_address, false),
+        AccountMeta::new_readonly(sender_access_pda_address, false),
+        AccountMeta::new(*wallet_address, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if let Some(replied_document_index) = in_reply_to {
+        accounts.push(AccountMeta::new_readonly(
+            Document::find_pda_address(replied_document_index, sender_address, &id()),
+            false,
+        ));
+    }
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::SendPreSignedDocument {
+            data: document_data,
+            nonce,
+            in_reply_to,
+            encryption_key_version,
+        },
+        accounts,
+    )
+}
+
+/// Creates the message the sender signs to authorize SendPreSignedDocument
+pub fn pre_signed_document_message(
+    program_id: &Pubkey,
+    sender_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_data: &DocumentPayload,
+    nonce: u64,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
+) -> Vec<u8> {
+    let mut message = vec![PRE_SIGNED_DOCUMENT_DOMAIN];
+    message.extend_from_slice(&program_id.to_bytes());
+    message.extend_from_slice(&sender_address.to_bytes());
+    message.extend_from_slice(&wallet_address.to_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&document_data.try_to_vec().unwrap());
+    message.extend_from_slice(&in_reply_to.try_to_vec().unwrap());
+    message.extend_from_slice(&encryption_key_version.to_le_bytes());
+
+    message
+}
+
+/// Creates CloseDocument instruction for the document stored at the given index. Batch
+/// documents take the funder of their content account.
+pub fn close_document(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    document: &Document,
+    content_funder_address: Option<&Pubkey>,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        &document.id,
+        &id(),
+    );
+
+    let sent_document_pda_address = SentDocument::find_pda_address(
+        document.sent_document_index,
+        &document.sender,
+        &id(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority_address, true),
+        AccountMeta::new(document_pda_address, false),
+        AccountMeta::new(document_locator_pda_address, false),
+        AccountMeta::new(*wallet_address, false),
+        AccountMeta::new(document.funder, false),
+        AccountMeta::new(sent_document_pda_address, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+
+    if let (DocumentData::Content { address, .. }, Some(content_funder_address)) =
+        (&document.data, content_funder_address)
+    {
+        accounts.push(AccountMeta::new(*address, false));
+        accounts.push(AccountMeta::new(*content_funder_address, false));
+    }
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::CloseDocument {
+            document_index,
+        },
+        accounts,
+    )
+}
+
+/// Creates UpdateReceiver instruction
+pub fn update_receiver(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    new_authority_address: Option<Pubkey>,
+    access_policy: Option<AccessPolicy>,
+    postage: Option<u64>,
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::UpdateReceiver {
+            authority: new_authority_address,
+            access_policy,
+            postage,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new(receiver_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates SetSenderAccess instruction
+pub fn set_sender_access(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    sender_address: &Pubkey,
+    access: Option<SenderAccess>,
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    let sender_access_pda_address = SenderAccessEntry::find_pda_address(
+        wallet_address,
+        sender_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::SetSenderAccess {
+            sender: *sender_address,
+            access,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new_readonly(receiver_pda_address, false),
+            AccountMeta::new(sender_access_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates MigrateReceiver instruction
+pub fn migrate_receiver(
+    funder_address: &Pubkey,
+    wallet_address: &Pubkey,
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::MigrateReceiver {},
+        vec![
+            AccountMeta::new(*funder_address, true),
+            AccountMeta::new(receiver_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates AddDelegate instruction
+pub fn add_delegate(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    delegate_address: &Pubkey,
+    expires_at: Option<UnixTimestamp>,
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    let delegate_pda_address = Delegate::find_pda_address(
+        wallet_address,
+        delegate_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::AddDelegate {
+            delegate: *delegate_address,
+            expires_at,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new_readonly(receiver_pda_address, false),
+            AccountMeta::new(delegate_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates RemoveDelegate instruction
+pub fn remove_delegate(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    delegate_address: &Pubkey,
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    let delegate_pda_address = Delegate::find_pda_address(
+        wallet_address,
+        delegate_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::RemoveDelegate {
+            delegate: *delegate_address,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new_readonly(receiver_pda_address, false),
+            AccountMeta::new(delegate_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+        ],
+    )
+}
+
+/// Creates RecallDocument instruction
+pub fn recall_document(
+    sender_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::RecallDocument {
+            document_index,
+        },
+        vec![
+            AccountMeta::new_readonly(*sender_address, true),
+            AccountMeta::new(document_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+    )
+}
+
+/// Creates SendDocumentToMany instruction for the receivers given with their next
+/// document index and encryption key version, the sent document indexes follow
+/// `sent_document_index`
+pub fn send_document_to_many(
+    funder_address: &Pubkey,
+    sender_address: &Pubkey,
+    receivers: &[(Pubkey, u32, u32)],
+    sent_document_index: u32,
+    document_data: DocumentPayload,
+    in_reply_to: Option<u32>,
+) -> Instruction {
+    let outbox_pda_address = Outbox::find_pda_address(
+        sender_address,
+        &id(),
+    );
+
+    let content_pda_address = Content::find_pda_address(
+        sent_document_index,
+        sender_address,
+        &id(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta::new(*funder_address, true),
+        AccountMeta::new_readonly(*sender_address, true),
+        AccountMeta::new(outbox_pda_address, false),
+        AccountMeta::new(content_pda_address, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if let Some(replied_document_index) = in_reply_to {
+        accounts.push(AccountMeta::new_readonly(
+            Document::find_pda_address(replied_document_index, sender_address, &id()),
+            false,
+        ));
+    }
+
+    let data = DocumentData::Content {
+        address: content_pda_address,
+        expires_at: document_data.expires_at,
+    };
+
+    for (receiver_sent_document_index, (wallet_address, document_index, _)) in
+        (sent_document_index..).zip(receivers)
+    {
+        let document_id = Document::compute_id(sender_address, wallet_address, *document_index, &data);
+
+        accounts.extend_from_slice(&[
+            AccountMeta::new(Receiver::find_pda_address(wallet_address, &id()), false),
+            AccountMeta::new(Document::find_pda_address(*document_index, wallet_address, &id()), false),
+            AccountMeta::new(DocumentLocator::find_pda_address(&document_id, &id()), false),
+            AccountMeta::new(
+                SentDocument::find_pda_address(receiver_sent_document_index, sender_address, &id()),
+                false,
+            ),
+            AccountMeta::new_readonly(
+                SenderAccessEntry::find_pda_address(wallet_address, sender_address, &id()),
+                false,
+            ),
+            AccountMeta::new(*wallet_address, false),
+        ]);
+    }
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::SendDocumentToMany {
+            data: document_data,
+            in_reply_to,
+            encryption_key_versions: receivers
+                .iter()
+                .map(|(_, _, encryption_key_version)| *encryption_key_version)
+                .collect(),
+        },
+        accounts,
+    )
+}
+
+/// Creates AcknowledgeDocument instruction
+pub fn acknowledge_document(
+    wallet_address: &Pubkey,
+    document_index: u32,
+    reason_hash: Option<[u8; 32]>,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::AcknowledgeDocument {
+            document_index,
+            reason_hash,
+        },
+        vec![
+            AccountMeta::new(*wallet_address, true),
+            AccountMeta::new(document_pda_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates RejectDocument instruction
+pub fn reject_document(
+    wallet_address: &Pubkey,
+    document_index: u32,
+    reason_hash: Option<[u8; 32]>,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::RejectDocument {
+            document_index,
+            reason_hash,
+        },
+        vec![
+            AccountMeta::new(*wallet_address, true),
+            AccountMeta::new(document_pda_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates SetEncryptionKey instruction
+pub fn set_encryption_key(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    scheme: EncryptionScheme,
+    public_key: [u8; 32],
+) -> Instruction {
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::SetEncryptionKey {
+            scheme,
+            public_key,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new(receiver_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates GrantDocumentAccess instruction
+pub fn grant_document_access(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    document_id: &[u8; 32],
+    grantee_address: &Pubkey,
+    wrapped_key: Vec<u8>,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let key_envelope_pda_address = KeyEnvelope::find_pda_address(
+        document_id,
+        grantee_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::GrantDocumentAccess {
+            document_index,
+            grantee: *grantee_address,
+            wrapped_key,
+        },
+        vec![
+            AccountMeta::new(*authority_address, true),
+            AccountMeta::new_readonly(document_pda_address, false),
+            AccountMeta::new(key_envelope_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates RevokeDocumentAccess instruction
+pub fn revoke_document_access(
+    authority_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    document_id: &[u8; 32],
+    grantee_address: &Pubkey,
+    granted_by_address: &Pubkey,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let key_envelope_pda_address = KeyEnvelope::find_pda_address(
+        document_id,
+        grantee_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::RevokeDocumentAccess {
+            document_index,
+            grantee: *grantee_address,
+        },
+        vec![
+            AccountMeta::new_readonly(*authority_address, true),
+            AccountMeta::new_readonly(document_pda_address, false),
+            AccountMeta::new(key_envelope_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new(*granted_by_address, false),
+        ],
+    )
+}
+
+/// Creates ForwardDocument instruction, the data of the original document is needed to
+/// derive the locator of the forwarded document
+#[allow(clippy::too_many_arguments)]
+pub fn forward_document(
+    funder_address: &Pubkey,
+    forwarder_address: &Pubkey,
+    original_document_index: u32,
+    original_document_data: &DocumentData,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    sent_document_index: u32,
+    encryption_key_version: u32,
+) -> Instruction {
+    let original_document_pda_address = Document::find_pda_address(
+        original_document_index,
+        forwarder_address,
+        &id(),
+    );
+
+    let receiver_pda_address = Receiver::find_pda_address(
+        wallet_address,
+        &id(),
+    );
+
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        &Document::compute_id(
+            forwarder_address,
+            wallet_address,
+            document_index,
+            original_document_data,
+        ),
+        &id(),
+    );
+
+    let outbox_pda_address = Outbox::find_pda_address(
+        forwarder_address,
+        &id(),
+    );
+
+    let sent_document_pda_address = SentDocument::find_pda_address(
+        sent_document_index,
+        forwarder_address,
+        &id(),
+    );
+
+    let sender_access_pda_address = SenderAccessEntry::find_pda_address(
+        wallet_address,
+        forwarder_address,
+        &id(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta::new(*funder_address, true),
+        AccountMeta::new_readonly(*forwarder_address, true),
+        AccountMeta::new_readonly(original_document_pda_address, false),
+        AccountMeta::new(receiver_pda_address, false),
+        AccountMeta::new(document_pda_address, false),
+        AccountMeta::new(document_locator_pda_address, false),
+        AccountMeta::new(outbox_pda_address, false),
+        AccountMeta::new(sent_document_pda_address, false),
+        AccountMeta::new_readonly(sender_access_pda_address, false),
+        AccountMeta::new(*wallet_address, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if let DocumentData::Content { address, .. } = original_document_data {
+        accounts.push(AccountMeta::new(*address, false));
+    }
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::ForwardDocument {
+            document_index: original_document_index,
+            encryption_key_version,
+        },
+        accounts,
+    )
+}
+
+/// Creates SetReceiverMetadata instruction
+pub fn set_receiver_metadata(
+    wallet_address: &Pubkey,
+    document_index: u32,
+    metadata: ReceiverMetadata,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::SetReceiverMetadata {
+            document_index,
+            metadata,
+        },
+        vec![
+            AccountMeta::new_readonly(*wallet_address, true),
+            AccountMeta::new(document_pda_address, false),
+        ],
+    )
+}
+
+/// Creates AmendDocument instruction
+pub fn amend_document(
+    funder_address: &Pubkey,
+    sender_address: &Pubkey,
+    wallet_address: &Pubkey,
+    document_index: u32,
+    document_data: DocumentPayload,
+) -> Instruction {
+    let document_pda_address = Document::find_pda_address(
+        document_index,
+        wallet_address,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::AmendDocument {
+            document_index,
+            data: document_data,
+        },
+        vec![
+            AccountMeta::new(*funder_address, true),
+            AccountMeta::new_readonly(*sender_address, true),
+            AccountMeta::new(document_pda_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates CloseKeyEnvelope instruction
+pub fn close_key_envelope(
+    granted_by_address: &Pubkey,
+    document_id: &[u8; 32],
+    grantee_address: &Pubkey,
+) -> Instruction {
+    let key_envelope_pda_address = KeyEnvelope::find_pda_address(
+        document_id,
+        grantee_address,
+        &id(),
+    );
+
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        document_id,
+        &id(),
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &DocumentsInstruction::CloseKeyEnvelope {
+            document_id: *document_id,
+            grantee: *grantee_address,
+        },
+        vec![
+            AccountMeta::new(*granted_by_address, true),
+            AccountMeta::new(key_envelope_pda_address, false),
+            AccountMeta::new_readonly(document_locator_pda_address, false),
+        ],
+    )
+}
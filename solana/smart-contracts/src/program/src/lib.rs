@@ -1,13 +1,18 @@
+pub mod error;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod utils;
 
-use solana_program::{
-    account_info::AccountInfo,
-    entrypoint,
-    entrypoint::ProgramResult,
-    pubkey::Pubkey,
+use {
+    crate::error::DocumentsError,
+    solana_program::{
+        account_info::AccountInfo,
+        entrypoint,
+        entrypoint::ProgramResult,
+        program_error::PrintProgramError,
+        pubkey::Pubkey,
+    },
 };
 
 entrypoint!(process_instruction);
@@ -19,5 +24,9 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    crate::processor::process_instruction(program_id, accounts, instruction_data)
+    if let Err(error) = crate::processor::process_instruction(program_id, accounts, instruction_data) {
+        error.print::<DocumentsError>();
+        return Err(error);
+    }
+    Ok(())
 }
\ No newline at end of file
@@ -2,21 +2,29 @@
 
 use {
     crate::{
-        instruction::DocumentsInstruction,
-        state::{Document, Receiver},
-        utils::create_pda_account,
+        error::DocumentsError,
+        instruction::{
+            pre_signed_document_message, DocumentsInstruction, SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS,
+        },
+        state::{
+            AccessPolicy, Content, Delegate, Document, DocumentData, DocumentLocator, DocumentPayload, DocumentStatus, EncryptionKey, EncryptionScheme, ForwardedFrom, KeyEnvelope, Outbox, Receipt, ReceiptStatus, Receiver, ReceiverMetadata,
+            SenderAccess, SenderAccessEntry, SenderNonce, SentDocument,
+        },
+        utils::{close_pda_account, create_pda_account, resize_account, transfer_lamports, verify_ed25519_signature},
     },
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         account_info::{next_account_info, AccountInfo},
-        clock::Clock,
+        clock::{Clock, UnixTimestamp},
         entrypoint::ProgramResult,
-        msg,
+        program::invoke,
         program_error::ProgramError,
         pubkey::Pubkey,
         rent::Rent,
+        system_instruction,
         sysvar::{
             clock,
+            instructions,
             rent,
             Sysvar,
         },
@@ -31,8 +39,27 @@ pub fn process_instruction(
     let instruction = DocumentsInstruction::try_from_slice(instruction_data)?;
 
     match instruction {
-        DocumentsInstruction::CreateReceiverAccount {} => create_receiver_account(program_id, accounts),
-        DocumentsInstruction::SendDocument { data } => send_document(program_id, accounts, data),
+        DocumentsInstruction::CreateReceiverAccount => create_receiver_account(program_id, accounts),
+        DocumentsInstruction::SendDocument { data, in_reply_to, encryption_key_version } => send_document(program_id, accounts, data, in_reply_to, encryption_key_version),
+        DocumentsInstruction::MarkDocumentOpened { document_index } => mark_document_opened(program_id, accounts, document_index),
+        DocumentsInstruction::SendPreSignedDocument { data, nonce, in_reply_to, encryption_key_version } => send_pre_signed_document(program_id, accounts, data, nonce, in_reply_to, encryption_key_version),
+        DocumentsInstruction::CloseDocument { document_index } => close_document(program_id, accounts, document_index),
+        DocumentsInstruction::UpdateReceiver { authority, access_policy, postage } => update_receiver(program_id, accounts, authority, access_policy, postage),
+        DocumentsInstruction::SetSenderAccess { sender, access } => set_sender_access(program_id, accounts, sender, access),
+        DocumentsInstruction::MigrateReceiver => migrate_receiver(program_id, accounts),
+        DocumentsInstruction::AddDelegate { delegate, expires_at } => add_delegate(program_id, accounts, delegate, expires_at),
+        DocumentsInstruction::RemoveDelegate { delegate } => remove_delegate(program_id, accounts, delegate),
+        DocumentsInstruction::RecallDocument { document_index } => recall_document(program_id, accounts, document_index),
+        DocumentsInstruction::SendDocumentToMany { data, in_reply_to, encryption_key_versions } => send_document_to_many(program_id, accounts, data, in_reply_to, encryption_key_versions),
+        DocumentsInstruction::AcknowledgeDocument { document_index, reason_hash } => issue_receipt(program_id, accounts, document_index, ReceiptStatus::Acknowledged, reason_hash),
+        DocumentsInstruction::RejectDocument { document_index, reason_hash } => issue_receipt(program_id, accounts, document_index, ReceiptStatus::Rejected, reason_hash),
+        DocumentsInstruction::SetEncryptionKey { scheme, public_key } => set_encryption_key(program_id, accounts, scheme, public_key),
+        DocumentsInstruction::GrantDocumentAccess { document_index, grantee, wrapped_key } => grant_document_access(program_id, accounts, document_index, grantee, wrapped_key),
+        DocumentsInstruction::RevokeDocumentAccess { document_index, grantee } => revoke_document_access(program_id, accounts, document_index, grantee),
+        DocumentsInstruction::ForwardDocument { document_index, encryption_key_version } => forward_document(program_id, accounts, document_index, encryption_key_version),
+        DocumentsInstruction::SetReceiverMetadata { document_index, metadata } => set_receiver_metadata(program_id, accounts, document_index, metadata),
+        DocumentsInstruction::AmendDocument { document_index, data } => amend_document(program_id, accounts, document_index, data),
+        DocumentsInstruction::CloseKeyEnvelope { document_id, grantee } => close_key_envelope(program_id, accounts, document_id, grantee),
     }
 }
 
@@ -57,24 +84,22 @@ fn create_receiver_account(
         );
 
     if receiver_address != *receiver_account_info.key {
-        msg!("Error: Receiver address does not match seed derivation");
-        return Err(ProgramError::InvalidSeeds);
+        return Err(DocumentsError::AddressDerivationMismatch.into());
     }
 
     if receiver_account_info.data.borrow().len() > 0 {
-        msg!("Error: Receiver account is already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(DocumentsError::ReceiverAlreadyInitialized.into());
     }
 
     let receiver_account_signer_seeds: &[&[_]] = &[
         &receiver_wallet_account_info.key.to_bytes(),
-        &Receiver::ACCOUNT_ADDRESS_SEED.as_bytes(),
+        Receiver::ACCOUNT_ADDRESS_SEED.as_bytes(),
         &[receiver_bump_seed],
     ];
 
     create_pda_account(
         funder_info,
-        &rent,
+        rent,
         Receiver::retrieve_size(),
         program_id,
         system_program_info,
@@ -82,57 +107,413 @@ fn create_receiver_account(
         receiver_account_signer_seeds,
     )?;
 
+    let mut receiver = Receiver::new();
+    receiver.authority = *receiver_wallet_account_info.key;
+    receiver.serialize(&mut &mut receiver_account_info.data.borrow_mut()[..])?;
+
     Ok(())
 }
 
 fn send_document(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    data: Vec<u8>,
+    data: DocumentPayload,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
+    let funder_info = next_account_info(account_info_iter)?;
     let sender_info = next_account_info(account_info_iter)?;
     let receiver_account_info = next_account_info(account_info_iter)?;
     let document_account_info = next_account_info(account_info_iter)?;
+    let document_locator_account_info = next_account_info(account_info_iter)?;
+    let outbox_account_info = next_account_info(account_info_iter)?;
+    let sent_document_account_info = next_account_info(account_info_iter)?;
+    let sender_access_account_info = next_account_info(account_info_iter)?;
     let receiver_wallet_account_info = next_account_info(account_info_iter)?;
     let rent_info = next_account_info(account_info_iter)?;
     let clock_info = next_account_info(account_info_iter)?;
     let system_program_info = next_account_info(account_info_iter)?;
 
-    let rent = &Rent::from_account_info(rent_info)?;
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
 
-    let receiver_address = Receiver::find_pda_address(
-        receiver_wallet_account_info.key,
+    if !sender_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if let Some(document_index) = in_reply_to {
+        check_replied_document(
+            program_id,
+            sender_info.key,
+            document_index,
+            next_account_info(account_info_iter)?,
+        )?;
+    }
+
+    create_document(
+        program_id,
+        funder_info,
+        sender_info.key,
+        receiver_account_info,
+        document_account_info,
+        document_locator_account_info,
+        outbox_account_info,
+        sent_document_account_info,
+        sender_access_account_info,
+        receiver_wallet_account_info,
+        rent_info,
+        clock_info,
+        system_program_info,
+        DocumentData::Payload(data),
+        in_reply_to,
+        None,
+        encryption_key_version,
+    )
+}
+
+fn send_pre_signed_document(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: DocumentPayload,
+    nonce: u64,
+    in_reply_to: Option<u32>,
+    encryption_key_version: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let funder_info = next_account_info(account_info_iter)?;
+    let sender_info = next_account_info(account_info_iter)?;
+    let sender_nonce_account_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let document_locator_account_info = next_account_info(account_info_iter)?;
+    let outbox_account_info = next_account_info(account_info_iter)?;
+    let sent_document_account_info = next_account_info(account_info_iter)?;
+    let sender_access_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let instructions_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let (sender_nonce_address, sender_nonce_bump_seed) =
+        SenderNonce::find_pda_address_with_bump_seed(
+            sender_info.key,
+            program_id,
+        );
+
+    if sender_nonce_address != *sender_nonce_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if !instructions::check_id(instructions_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let is_sender_nonce_initialized = sender_nonce_account_info.data.borrow().len() > 0;
+
+    let mut sender_nonce = if is_sender_nonce_initialized {
+        if sender_nonce_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        SenderNonce::try_from_slice(&sender_nonce_account_info.data.borrow())?
+    } else {
+        SenderNonce::new()
+    };
+
+    if sender_nonce.nonce != nonce {
+        return Err(DocumentsError::InvalidNonce.into());
+    }
+
+    verify_ed25519_signature(
+        instructions_info,
+        sender_info.key,
+        &pre_signed_document_message(
+            program_id,
+            sender_info.key,
+            receiver_wallet_account_info.key,
+            &data,
+            nonce,
+            in_reply_to,
+            encryption_key_version,
+        ),
+    )?;
+
+    if let Some(document_index) = in_reply_to {
+        check_replied_document(
+            program_id,
+            sender_info.key,
+            document_index,
+            next_account_info(account_info_iter)?,
+        )?;
+    }
+
+    // Create sender nonce PDA account on the first pre-signed document
+    if !is_sender_nonce_initialized {
+        let sender_nonce_account_signer_seeds: &[&[_]] = &[
+            &sender_info.key.to_bytes(),
+            SenderNonce::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            &[sender_nonce_bump_seed],
+        ];
+
+        create_pda_account(
+            funder_info,
+            &Rent::from_account_info(rent_info)?,
+            SenderNonce::retrieve_size(),
+            program_id,
+            system_program_info,
+            sender_nonce_account_info,
+            sender_nonce_account_signer_seeds,
+        )?;
+    }
+
+    create_document(
+        program_id,
+        funder_info,
+        sender_info.key,
+        receiver_account_info,
+        document_account_info,
+        document_locator_account_info,
+        outbox_account_info,
+        sent_document_account_info,
+        sender_access_account_info,
+        receiver_wallet_account_info,
+        rent_info,
+        clock_info,
+        system_program_info,
+        DocumentData::Payload(data),
+        in_reply_to,
+        None,
+        encryption_key_version,
+    )?;
+
+    // Increment and store the nonce so the signed message can not be replayed
+    sender_nonce.nonce += 1;
+    sender_nonce.serialize(&mut &mut sender_nonce_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn send_document_to_many(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: DocumentPayload,
+    in_reply_to: Option<u32>,
+    encryption_key_versions: Vec<u32>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let funder_info = next_account_info(account_info_iter)?;
+    let sender_info = next_account_info(account_info_iter)?;
+    let outbox_account_info = next_account_info(account_info_iter)?;
+    let content_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !sender_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if let Some(document_index) = in_reply_to {
+        check_replied_document(
+            program_id,
+            sender_info.key,
+            document_index,
+            next_account_info(account_info_iter)?,
+        )?;
+    }
+
+    let receivers_account_infos = account_info_iter.as_slice();
+
+    if receivers_account_infos.is_empty()
+        || !receivers_account_infos
+            .chunks_exact(SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS)
+            .remainder()
+            .is_empty()
+        || receivers_account_infos.len() / SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS != encryption_key_versions.len()
+    {
+        return Err(DocumentsError::InvalidReceiverAccounts.into());
+    }
+
+    data.validate()?;
+
+    let outbox_address = Outbox::find_pda_address(
+        sender_info.key,
         program_id,
     );
 
-    if receiver_address != *receiver_account_info.key {
-        msg!("Error: Receiver address does not match seed derivation");
-        return Err(ProgramError::InvalidSeeds);
+    if outbox_address != *outbox_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
     }
 
-    if receiver_account_info.data.borrow().len() == 0 {
-        msg!("Error: Receiver account is not initialized");
-        return Err(ProgramError::UninitializedAccount);
+    // The content is indexed by the sent document index of the first document of the batch
+    let sent_documents_counter = if outbox_account_info.data.borrow().len() > 0 {
+        if outbox_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        Outbox::try_from_slice(&outbox_account_info.data.borrow())?.documents_counter
+    } else {
+        0
+    };
+
+    let (content_address, content_bump_seed) = Content::find_pda_address_with_bump_seed(
+        sent_documents_counter,
+        sender_info.key,
+        program_id,
+    );
+
+    if content_address != *content_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if content_account_info.data.borrow().len() > 0 {
+        return Err(DocumentsError::ContentAlreadyExists.into());
+    }
+
+    if !rent::check_id(rent_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    // Create content PDA account
+    let account_seed = sent_documents_counter.to_string() + Content::ACCOUNT_ADDRESS_SEED;
+
+    let content_account_signer_seeds: &[&[_]] = &[
+        &sender_info.key.to_bytes(),
+        account_seed.as_bytes(),
+        &[content_bump_seed],
+    ];
+
+    create_pda_account(
+        funder_info,
+        &Rent::from_account_info(rent_info)?,
+        Content::retrieve_size(&data),
+        program_id,
+        system_program_info,
+        content_account_info,
+        content_account_signer_seeds,
+    )?;
+
+    let expires_at = data.expires_at;
+    let receivers_count = receivers_account_infos.len() / SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS;
+
+    let mut content = Content::new(data);
+    content.sender = *sender_info.key;
+    content.receivers_count = receivers_count as u32;
+    content.funder = *funder_info.key;
+    content.serialize(&mut &mut content_account_info.data.borrow_mut()[..])?;
+
+    // Create the lightweight document of every receiver referencing the content
+    for (receiver_account_infos, encryption_key_version) in receivers_account_infos
+        .chunks(SEND_DOCUMENT_TO_MANY_RECEIVER_ACCOUNTS)
+        .zip(encryption_key_versions)
+    {
+        create_document(
+            program_id,
+            funder_info,
+            sender_info.key,
+            &receiver_account_infos[0],
+            &receiver_account_infos[1],
+            &receiver_account_infos[2],
+            outbox_account_info,
+            &receiver_account_infos[3],
+            &receiver_account_infos[4],
+            &receiver_account_infos[5],
+            rent_info,
+            clock_info,
+            system_program_info,
+            DocumentData::Content {
+                address: content_address,
+                expires_at,
+            },
+            in_reply_to,
+            None,
+            encryption_key_version,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Creates the next document account of the receiver and stores the document
+#[allow(clippy::too_many_arguments)]
+fn create_document<'a>(
+    program_id: &Pubkey,
+    funder_info: &AccountInfo<'a>,
+    sender: &Pubkey,
+    receiver_account_info: &AccountInfo<'a>,
+    document_account_info: &AccountInfo<'a>,
+    document_locator_account_info: &AccountInfo<'a>,
+    outbox_account_info: &AccountInfo<'a>,
+    sent_document_account_info: &AccountInfo<'a>,
+    sender_access_account_info: &AccountInfo<'a>,
+    receiver_wallet_account_info: &AccountInfo<'a>,
+    rent_info: &AccountInfo<'a>,
+    clock_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    data: DocumentData,
+    in_reply_to: Option<u32>,
+    forwarded_from: Option<ForwardedFrom>,
+    encryption_key_version: u32,
+) -> ProgramResult {
+    if let DocumentData::Payload(payload) = &data {
+        payload.validate()?;
     }
 
+    let rent = &Rent::from_account_info(rent_info)?;
+
+    let mut receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
     if document_account_info.data.borrow().len() > 0 {
-        msg!("Error: Document account is already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(DocumentsError::DocumentAlreadyExists.into());
     }
 
-    if !sender_info.is_signer {
-        msg!("Error: Sender signature missing");
-        return Err(ProgramError::MissingRequiredSignature);
+    let sender_access_address = SenderAccessEntry::find_pda_address(
+        receiver_wallet_account_info.key,
+        sender,
+        program_id,
+    );
+
+    if sender_access_address != *sender_access_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
     }
 
-    if receiver_account_info.owner != program_id {
-        msg!("Error: Receiver account does not have the correct program id");
-        return Err(ProgramError::IncorrectProgramId);
+    let sender_access = if sender_access_account_info.data.borrow().len() > 0 {
+        if sender_access_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        Some(SenderAccessEntry::try_from_slice(&sender_access_account_info.data.borrow())?.access)
+    } else {
+        None
+    };
+
+    if !receiver.access_policy.allows(sender_access) {
+        return Err(DocumentsError::SenderNotAllowed.into());
+    }
+
+    // The sender encrypted the document to the key it read, so a rotation since then is an error
+    if receiver.encryption_key.version != encryption_key_version {
+        return Err(DocumentsError::EncryptionKeyVersionMismatch.into());
     }
 
-    let mut receiver: Receiver = Receiver::try_from_slice(&receiver_account_info.data.borrow())?;
     let documents_counter = receiver.documents_counter;
 
     let (document_address, document_bump_seed) = Document::find_pda_address_with_bump_seed(
@@ -142,26 +523,45 @@ fn send_document(
     );
 
     if document_address != *document_account_info.key {
-        msg!("Error: Document address does not match seed derivation");
-        return Err(ProgramError::InvalidSeeds);
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    let document_id = Document::compute_id(
+        sender,
+        receiver_wallet_account_info.key,
+        documents_counter,
+        &data,
+    );
+
+    let (document_locator_address, document_locator_bump_seed) =
+        DocumentLocator::find_pda_address_with_bump_seed(
+            &document_id,
+            program_id,
+        );
+
+    if document_locator_address != *document_locator_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
     }
 
     if !rent.is_exempt(
         receiver_account_info.lamports(),
         receiver_account_info.data_len(),
     ) {
-        msg!("Error: Receiver account is not rent exempt");
-        return Err(ProgramError::AccountNotRentExempt);
+        return Err(DocumentsError::NotRentExempt.into());
     }
 
     if !rent::check_id(rent_info.key) {
-        msg!("Error: Invalid rent system account");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(DocumentsError::InvalidSysvar.into());
     }
 
     if !clock::check_id(clock_info.key) {
-        msg!("Error: Invalid clock system account");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let sent_at = Clock::from_account_info(clock_info)?.unix_timestamp;
+
+    if matches!(data.expires_at(), Some(expires_at) if expires_at <= sent_at) {
+        return Err(DocumentsError::DocumentExpired.into());
     }
 
     // Create document PDA account
@@ -169,29 +569,1485 @@ fn send_document(
 
     let document_account_signer_seeds: &[&[_]] = &[
         &receiver_wallet_account_info.key.to_bytes(),
-        &account_seed.as_bytes(),
+        account_seed.as_bytes(),
         &[document_bump_seed],
     ];
 
     create_pda_account(
-        sender_info,
-        &rent,
-        Document::retrieve_size(data.len()),
+        funder_info,
+        rent,
+        Document::retrieve_size(&data, in_reply_to, forwarded_from),
         program_id,
         system_program_info,
         document_account_info,
         document_account_signer_seeds,
     )?;
 
+    let sent_document_index = add_sent_document(
+        program_id,
+        funder_info,
+        sender,
+        outbox_account_info,
+        sent_document_account_info,
+        receiver_wallet_account_info,
+        documents_counter,
+        rent,
+        system_program_info,
+    )?;
+
     // Store document data
-    let mut document = Document::new(data.len());
-    document.sender = *sender_info.key;
-    document.data = data;
-    document.sent_at = Clock::from_account_info(clock_info)?.unix_timestamp;
+    let mut document = Document::new(data);
+    document.sender = *sender;
+    document.sent_at = sent_at;
+    document.in_reply_to = in_reply_to;
+    document.id = document_id;
+    document.encryption_key_version = encryption_key_version;
+    document.forwarded_from = forwarded_from;
+    document.funder = *funder_info.key;
+    document.sent_document_index = sent_document_index;
     document.serialize(&mut &mut document_account_info.data.borrow_mut()[..])?;
 
+    // Create document locator PDA account, so the document can be looked up by its ID
+    let document_locator_account_signer_seeds: &[&[_]] = &[
+        &document_id,
+        DocumentLocator::ACCOUNT_ADDRESS_SEED.as_bytes(),
+        &[document_locator_bump_seed],
+    ];
+
+    create_pda_account(
+        funder_info,
+        rent,
+        DocumentLocator::retrieve_size(),
+        program_id,
+        system_program_info,
+        document_locator_account_info,
+        document_locator_account_signer_seeds,
+    )?;
+
+    let document_locator = DocumentLocator {
+        receiver: *receiver_wallet_account_info.key,
+        document_index: documents_counter,
+    };
+    document_locator.serialize(&mut &mut document_locator_account_info.data.borrow_mut()[..])?;
+
+    // Pay the postage of the receiver
+    if receiver.postage > 0 {
+        invoke(
+            &system_instruction::transfer(
+                funder_info.key,
+                receiver_wallet_account_info.key,
+                receiver.postage,
+            ),
+            &[
+                funder_info.clone(),
+                receiver_wallet_account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
     // Increment and store the number of documents the receiver account has
     receiver.documents_counter += 1;
+    store_receiver(
+        &receiver,
+        funder_info,
+        receiver_account_info,
+        rent,
+        system_program_info,
+    )
+}
+
+fn forward_document(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    encryption_key_version: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let funder_info = next_account_info(account_info_iter)?;
+    let forwarder_info = next_account_info(account_info_iter)?;
+    let original_document_account_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let document_locator_account_info = next_account_info(account_info_iter)?;
+    let outbox_account_info = next_account_info(account_info_iter)?;
+    let sent_document_account_info = next_account_info(account_info_iter)?;
+    let sender_access_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !forwarder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    // The forwarder must be the receiver of the original document
+    let original_document_address = Document::find_pda_address(
+        document_index,
+        forwarder_info.key,
+        program_id,
+    );
+
+    if original_document_address != *original_document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if original_document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if original_document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    if !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let clock = Clock::from_account_info(clock_info)?;
+
+    let original_document: Document = Document::unpack(&original_document_account_info.data.borrow())?;
+
+    match original_document.status(clock.unix_timestamp) {
+        DocumentStatus::Unopened | DocumentStatus::Opened => {}
+        DocumentStatus::Revoked => return Err(DocumentsError::DocumentRevoked.into()),
+        DocumentStatus::Expired => return Err(DocumentsError::DocumentExpired.into()),
+    }
+
+    // The forwarded document references the content of a batch document as well
+    if let DocumentData::Content { address, .. } = original_document.data {
+        let content_account_info = next_account_info(account_info_iter)?;
+
+        if address != *content_account_info.key {
+            return Err(DocumentsError::AddressDerivationMismatch.into());
+        }
+
+        if content_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        let mut content = Content::try_from_slice(&content_account_info.data.borrow())?;
+        content.receivers_count = content.receivers_count
+            .checked_add(1)
+            .ok_or(DocumentsError::Overflow)?;
+        content.serialize(&mut &mut content_account_info.data.borrow_mut()[..])?;
+    }
+
+    let forwarded_from = ForwardedFrom {
+        receiver: *forwarder_info.key,
+        document_index,
+        sender: original_document.sender,
+        sent_at: original_document.sent_at,
+    };
+
+    // The forwarded document shares the content account of a batch document
+    create_document(
+        program_id,
+        funder_info,
+        forwarder_info.key,
+        receiver_account_info,
+        document_account_info,
+        document_locator_account_info,
+        outbox_account_info,
+        sent_document_account_info,
+        sender_access_account_info,
+        receiver_wallet_account_info,
+        rent_info,
+        clock_info,
+        system_program_info,
+        original_document.data,
+        None,
+        Some(forwarded_from),
+        encryption_key_version,
+    )?;
+
+    Ok(())
+}
+
+/// Stores the reference to the receiver's document in the outbox of the sender and returns
+/// the index of the sent document
+#[allow(clippy::too_many_arguments)]
+fn add_sent_document<'a>(
+    program_id: &Pubkey,
+    funder_info: &AccountInfo<'a>,
+    sender: &Pubkey,
+    outbox_account_info: &AccountInfo<'a>,
+    sent_document_account_info: &AccountInfo<'a>,
+    receiver_wallet_account_info: &AccountInfo<'a>,
+    document_index: u32,
+    rent: &Rent,
+    system_program_info: &AccountInfo<'a>,
+) -> Result<u32, ProgramError> {
+    let (outbox_address, outbox_bump_seed) = Outbox::find_pda_address_with_bump_seed(
+        sender,
+        program_id,
+    );
+
+    if outbox_address != *outbox_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    let is_outbox_initialized = outbox_account_info.data.borrow().len() > 0;
+
+    let mut outbox = if is_outbox_initialized {
+        if outbox_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        Outbox::try_from_slice(&outbox_account_info.data.borrow())?
+    } else {
+        // Create outbox PDA account on the first document of the sender
+        let outbox_account_signer_seeds: &[&[_]] = &[
+            &sender.to_bytes(),
+            Outbox::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            &[outbox_bump_seed],
+        ];
+
+        create_pda_account(
+            funder_info,
+            rent,
+            Outbox::retrieve_size(),
+            program_id,
+            system_program_info,
+            outbox_account_info,
+            outbox_account_signer_seeds,
+        )?;
+
+        Outbox::new()
+    };
+
+    let sent_documents_counter = outbox.documents_counter;
+
+    let (sent_document_address, sent_document_bump_seed) =
+        SentDocument::find_pda_address_with_bump_seed(
+            sent_documents_counter,
+            sender,
+            program_id,
+        );
+
+    if sent_document_address != *sent_document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if sent_document_account_info.data.borrow().len() > 0 {
+        return Err(DocumentsError::SentDocumentAlreadyExists.into());
+    }
+
+    // Create sent document PDA account
+    let account_seed = sent_documents_counter.to_string() + SentDocument::ACCOUNT_ADDRESS_SEED;
+
+    let sent_document_account_signer_seeds: &[&[_]] = &[
+        &sender.to_bytes(),
+        account_seed.as_bytes(),
+        &[sent_document_bump_seed],
+    ];
+
+    create_pda_account(
+        funder_info,
+        rent,
+        SentDocument::retrieve_size(),
+        program_id,
+        system_program_info,
+        sent_document_account_info,
+        sent_document_account_signer_seeds,
+    )?;
+
+    let sent_document = SentDocument {
+        receiver: *receiver_wallet_account_info.key,
+        document_index,
+    };
+    sent_document.serialize(&mut &mut sent_document_account_info.data.borrow_mut()[..])?;
+
+    // Increment and store the number of documents the sender has sent
+    outbox.documents_counter += 1;
+    outbox.serialize(&mut &mut outbox_account_info.data.borrow_mut()[..])?;
+
+    Ok(sent_documents_counter)
+}
+
+fn mark_document_opened(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let signer_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+
+    if !signer_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let clock = Clock::from_account_info(clock_info)?;
+
+    // The delegate passes the receiver wallet and its delegate account
+    let receiver_wallet_address = match next_account_info(account_info_iter) {
+        Ok(receiver_wallet_account_info) => {
+            let delegate_account_info = next_account_info(account_info_iter)?;
+
+            check_delegate(
+                program_id,
+                receiver_wallet_account_info.key,
+                signer_info.key,
+                delegate_account_info,
+                clock.unix_timestamp,
+            )?;
+
+            *receiver_wallet_account_info.key
+        }
+        Err(_) => *signer_info.key,
+    };
+
+    let (document_address, _document_bump_seed) = Document::find_pda_address_with_bump_seed(
+        document_index,
+        &receiver_wallet_address,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let mut document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    match document.status(clock.unix_timestamp) {
+        DocumentStatus::Unopened => {}
+        DocumentStatus::Opened => return Err(DocumentsError::AlreadyOpened.into()),
+        DocumentStatus::Revoked => return Err(DocumentsError::DocumentRevoked.into()),
+        DocumentStatus::Expired => return Err(DocumentsError::DocumentExpired.into()),
+    }
+
+    // Store the time at which the receiver opened the document
+    document.opened_at = clock.unix_timestamp;
+    document.serialize(&mut &mut document_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn close_document(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let document_locator_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let sent_document_account_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    if !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let clock = Clock::from_account_info(clock_info)?;
+
+    let document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    let status = document.status(clock.unix_timestamp);
+
+    if *authority_info.key == *receiver_wallet_account_info.key {
+        if status == DocumentStatus::Unopened {
+            return Err(DocumentsError::NotOpened.into());
+        }
+    } else if *authority_info.key == document.sender {
+        // The sender can not take back a document the receiver opened
+        match status {
+            DocumentStatus::Revoked | DocumentStatus::Expired => {}
+            DocumentStatus::Opened => return Err(DocumentsError::AlreadyOpened.into()),
+            DocumentStatus::Unopened => return Err(DocumentsError::DocumentNotRevokedOrExpired.into()),
+        }
+    } else {
+        return Err(DocumentsError::NotReceiverOrSender.into());
+    }
+
+    if document.funder != *funder_info.key {
+        return Err(DocumentsError::NotFunder.into());
+    }
+
+    let document_locator_address = DocumentLocator::find_pda_address(
+        &document.id,
+        program_id,
+    );
+
+    if document_locator_address != *document_locator_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    let sent_document_address = SentDocument::find_pda_address(
+        document.sent_document_index,
+        &document.sender,
+        program_id,
+    );
+
+    if sent_document_address != *sent_document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if sent_document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    // Release the content shared by the documents of a batch, the last document closes it
+    if let DocumentData::Content { address, .. } = document.data {
+        let content_account_info = next_account_info(account_info_iter)?;
+        let content_funder_info = next_account_info(account_info_iter)?;
+
+        if address != *content_account_info.key {
+            return Err(DocumentsError::AddressDerivationMismatch.into());
+        }
+
+        if content_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+
+        let mut content = Content::try_from_slice(&content_account_info.data.borrow())?;
+        content.receivers_count = content.receivers_count
+            .checked_sub(1)
+            .ok_or(DocumentsError::Overflow)?;
+
+        if content.receivers_count == 0 {
+            if content.funder != *content_funder_info.key {
+                return Err(DocumentsError::NotFunder.into());
+            }
+
+            close_pda_account(content_account_info, content_funder_info)?;
+        } else {
+            content.serialize(&mut &mut content_account_info.data.borrow_mut()[..])?;
+        }
+    }
+
+    // Return the rent of the receipt to the receiver, who paid it when issuing the receipt
+    if let Some(receipt) = &document.receipt {
+        transfer_lamports(document_account_info, receiver_wallet_account_info, receipt.rent)?;
+    }
+
+    // Return the rent to the funder and wipe the document with its locator and the reference
+    // in the outbox of the sender
+    close_pda_account(sent_document_account_info, funder_info)?;
+    close_pda_account(document_locator_account_info, funder_info)?;
+    close_pda_account(document_account_info, funder_info)
+}
+
+fn recall_document(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let sender_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+
+    if !sender_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    if !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let mut document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    if document.sender != *sender_info.key {
+        return Err(DocumentsError::NotSender.into());
+    }
+
+    // Expired documents can be recalled, so the sender can close them afterwards
+    if document.opened_at != 0 {
+        return Err(DocumentsError::AlreadyOpened.into());
+    }
+
+    if document.revoked_at != 0 {
+        return Err(DocumentsError::DocumentRevoked.into());
+    }
+
+    document.revoked_at = Clock::from_account_info(clock_info)?.unix_timestamp;
+    document.serialize(&mut &mut document_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn amend_document(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    data: DocumentPayload,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let funder_info = next_account_info(account_info_iter)?;
+    let sender_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !sender_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    data.validate()?;
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    if !rent::check_id(rent_info.key) || !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let rent = &Rent::from_account_info(rent_info)?;
+    let clock = Clock::from_account_info(clock_info)?;
+
+    let mut document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    if document.sender != *sender_info.key {
+        return Err(DocumentsError::NotSender.into());
+    }
+
+    // The rent of the resized account is returned to the funder on close
+    if document.funder != *funder_info.key {
+        return Err(DocumentsError::NotFunder.into());
+    }
+
+    match document.status(clock.unix_timestamp) {
+        DocumentStatus::Unopened => {}
+        DocumentStatus::Opened => return Err(DocumentsError::AlreadyOpened.into()),
+        DocumentStatus::Revoked => return Err(DocumentsError::DocumentRevoked.into()),
+        DocumentStatus::Expired => return Err(DocumentsError::DocumentExpired.into()),
+    }
+
+    // The content of a batch is shared with the other receivers
+    if let DocumentData::Content { .. } = document.data {
+        return Err(DocumentsError::SharedContentNotAmendable.into());
+    }
+
+    if matches!(data.expires_at, Some(expires_at) if expires_at <= clock.unix_timestamp) {
+        return Err(DocumentsError::DocumentExpired.into());
+    }
+
+    document.data = DocumentData::Payload(data);
+    document.amended_at = clock.unix_timestamp;
+    document.amendments_count = document.amendments_count
+        .checked_add(1)
+        .ok_or(DocumentsError::Overflow)?;
+
+    // Resize the document account to fit the new payload
+    let document_data = document.try_to_vec()?;
+
+    resize_account(
+        funder_info,
+        rent,
+        document_data.len(),
+        system_program_info,
+        document_account_info,
+    )?;
+    document_account_info.data.borrow_mut().copy_from_slice(&document_data);
+
+    Ok(())
+}
+
+fn issue_receipt(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    status: ReceiptStatus,
+    reason_hash: Option<[u8; 32]>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !receiver_wallet_account_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    if !rent::check_id(rent_info.key) || !clock::check_id(clock_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let rent = &Rent::from_account_info(rent_info)?;
+    let clock = Clock::from_account_info(clock_info)?;
+
+    let mut document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    if document.receipt.is_some() {
+        return Err(DocumentsError::ReceiptAlreadyIssued.into());
+    }
+
+    // Issuing the receipt opens the unopened document
+    match document.status(clock.unix_timestamp) {
+        DocumentStatus::Unopened => document.opened_at = clock.unix_timestamp,
+        DocumentStatus::Opened => {}
+        DocumentStatus::Revoked => return Err(DocumentsError::DocumentRevoked.into()),
+        DocumentStatus::Expired => return Err(DocumentsError::DocumentExpired.into()),
+    }
+
+    let mut receipt = Receipt {
+        status,
+        reason_hash,
+        issued_at: clock.unix_timestamp,
+        rent: 0,
+    };
+
+    // Grow the document account to fit the receipt. The receiver pays the rent of the extra
+    // space, which is returned to it when the document is closed.
+    let document_size = document_account_info.data_len() + receipt.try_to_vec()?.len();
+    receipt.rent = rent.minimum_balance(document_size)
+        .saturating_sub(document_account_info.lamports());
+    document.receipt = Some(receipt);
+
+    let document_data = document.try_to_vec()?;
+
+    resize_account(
+        receiver_wallet_account_info,
+        rent,
+        document_data.len(),
+        system_program_info,
+        document_account_info,
+    )?;
+    document_account_info.data.borrow_mut().copy_from_slice(&document_data);
+
+    Ok(())
+}
+
+fn set_receiver_metadata(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    metadata: ReceiverMetadata,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+
+    if !receiver_wallet_account_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let mut document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    // The inbox state has a fixed size, so the document account does not need to grow
+    document.receiver_metadata = metadata;
+    document.serialize(&mut &mut document_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn grant_document_access(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    grantee: Pubkey,
+    wrapped_key: Vec<u8>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let key_envelope_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if wrapped_key.is_empty() || wrapped_key.len() > KeyEnvelope::MAX_WRAPPED_KEY_LENGTH {
+        return Err(DocumentsError::InvalidWrappedKey.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    if *authority_info.key != *receiver_wallet_account_info.key
+        && *authority_info.key != document.sender
+    {
+        return Err(DocumentsError::NotReceiverOrSender.into());
+    }
+
+    let (key_envelope_address, key_envelope_bump_seed) = KeyEnvelope::find_pda_address_with_bump_seed(
+        &document.id,
+        &grantee,
+        program_id,
+    );
+
+    if key_envelope_address != *key_envelope_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if key_envelope_account_info.data.borrow().len() > 0 {
+        return Err(DocumentsError::KeyEnvelopeAlreadyExists.into());
+    }
+
+    if !rent::check_id(rent_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    // Create key envelope PDA account
+    let key_envelope_account_signer_seeds: &[&[_]] = &[
+        &document.id,
+        &grantee.to_bytes(),
+        KeyEnvelope::ACCOUNT_ADDRESS_SEED.as_bytes(),
+        &[key_envelope_bump_seed],
+    ];
+
+    create_pda_account(
+        authority_info,
+        &Rent::from_account_info(rent_info)?,
+        KeyEnvelope::retrieve_size(&wrapped_key),
+        program_id,
+        system_program_info,
+        key_envelope_account_info,
+        key_envelope_account_signer_seeds,
+    )?;
+
+    let mut key_envelope = KeyEnvelope::new(wrapped_key);
+    key_envelope.granted_by = *authority_info.key;
+    key_envelope.serialize(&mut &mut key_envelope_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn revoke_document_access(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_index: u32,
+    grantee: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let document_account_info = next_account_info(account_info_iter)?;
+    let key_envelope_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let granted_by_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let document_address = Document::find_pda_address(
+        document_index,
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let document: Document = Document::unpack(&document_account_info.data.borrow())?;
+
+    if *authority_info.key != *receiver_wallet_account_info.key
+        && *authority_info.key != document.sender
+    {
+        return Err(DocumentsError::NotReceiverOrSender.into());
+    }
+
+    let key_envelope_address = KeyEnvelope::find_pda_address(
+        &document.id,
+        &grantee,
+        program_id,
+    );
+
+    if key_envelope_address != *key_envelope_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if key_envelope_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::KeyEnvelopeNotFound.into());
+    }
+
+    if key_envelope_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let key_envelope = KeyEnvelope::try_from_slice(&key_envelope_account_info.data.borrow())?;
+
+    if key_envelope.granted_by != *granted_by_info.key {
+        return Err(DocumentsError::NotGrantor.into());
+    }
+
+    // Return the rent to the party that granted the access and wipe the key envelope data
+    close_pda_account(key_envelope_account_info, granted_by_info)?;
+
+    Ok(())
+}
+
+fn close_key_envelope(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    document_id: [u8; 32],
+    grantee: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let granted_by_info = next_account_info(account_info_iter)?;
+    let key_envelope_account_info = next_account_info(account_info_iter)?;
+    let document_locator_account_info = next_account_info(account_info_iter)?;
+
+    if !granted_by_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let key_envelope_address = KeyEnvelope::find_pda_address(
+        &document_id,
+        &grantee,
+        program_id,
+    );
+
+    if key_envelope_address != *key_envelope_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if key_envelope_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::KeyEnvelopeNotFound.into());
+    }
+
+    if key_envelope_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let document_locator_address = DocumentLocator::find_pda_address(
+        &document_id,
+        program_id,
+    );
+
+    if document_locator_address != *document_locator_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    // The document locator is closed with the document, while the document is open the
+    // access is revoked through RevokeDocumentAccess
+    if document_locator_account_info.data.borrow().len() > 0 {
+        return Err(DocumentsError::DocumentNotClosed.into());
+    }
+
+    let key_envelope = KeyEnvelope::try_from_slice(&key_envelope_account_info.data.borrow())?;
+
+    if key_envelope.granted_by != *granted_by_info.key {
+        return Err(DocumentsError::NotGrantor.into());
+    }
+
+    // Return the rent to the party that granted the access and wipe the key envelope data
+    close_pda_account(key_envelope_account_info, granted_by_info)?;
+
+    Ok(())
+}
+
+fn update_receiver(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority: Option<Pubkey>,
+    access_policy: Option<AccessPolicy>,
+    postage: Option<u64>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !rent::check_id(rent_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let mut receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    if receiver.authority != *authority_info.key {
+        return Err(DocumentsError::InvalidAuthority.into());
+    }
+
+    if let Some(authority) = authority {
+        receiver.authority = authority;
+    }
+
+    if let Some(access_policy) = access_policy {
+        receiver.access_policy = access_policy;
+    }
+
+    if let Some(postage) = postage {
+        receiver.postage = postage;
+    }
+
+    store_receiver(
+        &receiver,
+        authority_info,
+        receiver_account_info,
+        &Rent::from_account_info(rent_info)?,
+        system_program_info,
+    )
+}
+
+fn set_encryption_key(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    scheme: EncryptionScheme,
+    public_key: [u8; 32],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !rent::check_id(rent_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    if scheme == EncryptionScheme::None {
+        return Err(DocumentsError::InvalidEncryptionKey.into());
+    }
+
+    let mut receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    if receiver.authority != *authority_info.key {
+        return Err(DocumentsError::InvalidAuthority.into());
+    }
+
+    receiver.encryption_key = EncryptionKey {
+        scheme,
+        public_key,
+        version: receiver.encryption_key.version
+            .checked_add(1)
+            .ok_or(DocumentsError::Overflow)?,
+    };
+
+    store_receiver(
+        &receiver,
+        authority_info,
+        receiver_account_info,
+        &Rent::from_account_info(rent_info)?,
+        system_program_info,
+    )?;
+
+    Ok(())
+}
+
+fn set_sender_access(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sender: Pubkey,
+    access: Option<SenderAccess>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let sender_access_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    if receiver.authority != *authority_info.key {
+        return Err(DocumentsError::InvalidAuthority.into());
+    }
+
+    let (sender_access_address, sender_access_bump_seed) =
+        SenderAccessEntry::find_pda_address_with_bump_seed(
+            receiver_wallet_account_info.key,
+            &sender,
+            program_id,
+        );
+
+    if sender_access_address != *sender_access_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    let is_sender_access_initialized = sender_access_account_info.data.borrow().len() > 0;
+
+    if is_sender_access_initialized && sender_access_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let access = match access {
+        Some(access) => access,
+        None => {
+            if !is_sender_access_initialized {
+                return Err(DocumentsError::SenderAccessNotFound.into());
+            }
+
+            // Return the rent to the authority and wipe the sender access data
+            return close_pda_account(sender_access_account_info, authority_info);
+        }
+    };
+
+    // Create sender access PDA account on the first access set for the sender
+    if !is_sender_access_initialized {
+        if !rent::check_id(rent_info.key) {
+            return Err(DocumentsError::InvalidSysvar.into());
+        }
+
+        let sender_access_account_signer_seeds: &[&[_]] = &[
+            &receiver_wallet_account_info.key.to_bytes(),
+            &sender.to_bytes(),
+            SenderAccessEntry::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            &[sender_access_bump_seed],
+        ];
+
+        create_pda_account(
+            authority_info,
+            &Rent::from_account_info(rent_info)?,
+            SenderAccessEntry::retrieve_size(),
+            program_id,
+            system_program_info,
+            sender_access_account_info,
+            sender_access_account_signer_seeds,
+        )?;
+    }
+
+    SenderAccessEntry::new(access)
+        .serialize(&mut &mut sender_access_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn migrate_receiver(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let funder_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !funder_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    if !rent::check_id(rent_info.key) {
+        return Err(DocumentsError::InvalidSysvar.into());
+    }
+
+    let receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    store_receiver(
+        &receiver,
+        funder_info,
+        receiver_account_info,
+        &Rent::from_account_info(rent_info)?,
+        system_program_info,
+    )
+}
+
+fn add_delegate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+    expires_at: Option<UnixTimestamp>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let delegate_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    if receiver.authority != *authority_info.key {
+        return Err(DocumentsError::InvalidAuthority.into());
+    }
+
+    let (delegate_address, delegate_bump_seed) = Delegate::find_pda_address_with_bump_seed(
+        receiver_wallet_account_info.key,
+        &delegate,
+        program_id,
+    );
+
+    if delegate_address != *delegate_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if delegate_account_info.data.borrow().len() > 0 {
+        if delegate_account_info.owner != program_id {
+            return Err(DocumentsError::IncorrectOwner.into());
+        }
+    } else {
+        if !rent::check_id(rent_info.key) {
+            return Err(DocumentsError::InvalidSysvar.into());
+        }
+
+        // Create delegate PDA account
+        let delegate_account_signer_seeds: &[&[_]] = &[
+            &receiver_wallet_account_info.key.to_bytes(),
+            &delegate.to_bytes(),
+            Delegate::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            &[delegate_bump_seed],
+        ];
+
+        create_pda_account(
+            authority_info,
+            &Rent::from_account_info(rent_info)?,
+            Delegate::retrieve_size(),
+            program_id,
+            system_program_info,
+            delegate_account_info,
+            delegate_account_signer_seeds,
+        )?;
+    }
+
+    Delegate::new(expires_at)
+        .serialize(&mut &mut delegate_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn remove_delegate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority_info = next_account_info(account_info_iter)?;
+    let receiver_account_info = next_account_info(account_info_iter)?;
+    let delegate_account_info = next_account_info(account_info_iter)?;
+    let receiver_wallet_account_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(DocumentsError::MissingSignature.into());
+    }
+
+    let receiver = load_receiver(
+        program_id,
+        receiver_account_info,
+        receiver_wallet_account_info,
+    )?;
+
+    if receiver.authority != *authority_info.key {
+        return Err(DocumentsError::InvalidAuthority.into());
+    }
+
+    let delegate_address = Delegate::find_pda_address(
+        receiver_wallet_account_info.key,
+        &delegate,
+        program_id,
+    );
+
+    if delegate_address != *delegate_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if delegate_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DelegateNotFound.into());
+    }
+
+    if delegate_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    // Return the rent to the authority and wipe the delegate data
+    close_pda_account(delegate_account_info, authority_info)
+}
+
+/// Checks that the delegate account authorizes the delegate to act for the receiver
+fn check_delegate(
+    program_id: &Pubkey,
+    receiver_wallet_address: &Pubkey,
+    delegate_address: &Pubkey,
+    delegate_account_info: &AccountInfo,
+    now: UnixTimestamp,
+) -> ProgramResult {
+    let delegate_pda_address = Delegate::find_pda_address(
+        receiver_wallet_address,
+        delegate_address,
+        program_id,
+    );
+
+    if delegate_pda_address != *delegate_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if delegate_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DelegateNotFound.into());
+    }
+
+    if delegate_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let delegate = Delegate::try_from_slice(&delegate_account_info.data.borrow())?;
+
+    if !delegate.is_active(now) {
+        return Err(DocumentsError::DelegateExpired.into());
+    }
+
+    Ok(())
+}
+
+/// Checks the replied document is a document the sender received
+fn check_replied_document(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    document_index: u32,
+    document_account_info: &AccountInfo,
+) -> ProgramResult {
+    let document_address = Document::find_pda_address(
+        document_index,
+        sender,
+        program_id,
+    );
+
+    if document_address != *document_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if document_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::DocumentNotFound.into());
+    }
+
+    if document_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    Ok(())
+}
+
+/// Loads the receiver account of the wallet and migrates it to the current version
+fn load_receiver(
+    program_id: &Pubkey,
+    receiver_account_info: &AccountInfo,
+    receiver_wallet_account_info: &AccountInfo,
+) -> Result<Receiver, ProgramError> {
+    let receiver_address = Receiver::find_pda_address(
+        receiver_wallet_account_info.key,
+        program_id,
+    );
+
+    if receiver_address != *receiver_account_info.key {
+        return Err(DocumentsError::AddressDerivationMismatch.into());
+    }
+
+    if receiver_account_info.data.borrow().len() == 0 {
+        return Err(DocumentsError::ReceiverNotInitialized.into());
+    }
+
+    if receiver_account_info.owner != program_id {
+        return Err(DocumentsError::IncorrectOwner.into());
+    }
+
+    let mut receiver = Receiver::unpack(&receiver_account_info.data.borrow())?;
+    receiver.migrate(receiver_wallet_account_info.key);
+
+    Ok(receiver)
+}
+
+/// Stores the receiver account, growing accounts of older versions to the current size
+fn store_receiver<'a>(
+    receiver: &Receiver,
+    payer_info: &AccountInfo<'a>,
+    receiver_account_info: &AccountInfo<'a>,
+    rent: &Rent,
+    system_program_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    resize_account(
+        payer_info,
+        rent,
+        Receiver::retrieve_size(),
+        system_program_info,
+        receiver_account_info,
+    )?;
     receiver.serialize(&mut &mut receiver_account_info.data.borrow_mut()[..])?;
 
     Ok(())
@@ -1,7 +1,10 @@
 use {
+    crate::error::DocumentsError,
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         clock::UnixTimestamp,
+        hash::hashv,
+        program_error::ProgramError,
         pubkey::{
             Pubkey,
         },
@@ -10,25 +13,68 @@ use {
 };
 
 /// Define a receiver account structure
+///
+/// Fields are only ever appended, so accounts of older versions can be read with
+/// `unpack` and upgraded with `migrate`.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct Receiver {
     /// Number of documents
     pub documents_counter: u32,
+    /// Policy deciding which senders can send documents to the receiver
+    pub access_policy: AccessPolicy,
+    /// Version of the account layout, zero for accounts created before versioning
+    pub version: u8,
+    /// Key allowed to configure the receiver account
+    pub authority: Pubkey,
+    /// Lamports the funder of a document pays to the receiver wallet, zero for free inboxes
+    pub postage: u64,
+    /// Public key senders encrypt the documents of the receiver to
+    pub encryption_key: EncryptionKey,
 }
 
 impl Receiver {
     pub const ACCOUNT_ADDRESS_SEED: &'static str = "receiver";
+    pub const VERSION: u8 = 3;
 
     /// Create a new dummy receiver account
     pub fn new() -> Self {
         Self {
             documents_counter: 0,
+            access_policy: AccessPolicy::Open,
+            version: Self::VERSION,
+            authority: Pubkey::default(),
+            postage: 0,
+            encryption_key: EncryptionKey::new(),
         }
     }
 
     /// Get size of receiver account
     pub fn retrieve_size() -> usize {
-        mem::size_of::<Receiver>()
+        Self::new().try_to_vec().unwrap().len()
+    }
+
+    /// Deserialize the receiver account, including accounts created before fields were
+    /// appended to the structure. Missing fields are read as zero bytes, so every appended
+    /// field must default to its zero encoding.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut buffer = data.to_vec();
+
+        if buffer.len() < Self::retrieve_size() {
+            buffer.resize(Self::retrieve_size(), 0);
+        }
+
+        Ok(Self::try_from_slice(&buffer)?)
+    }
+
+    /// Upgrade the fields of an account of an older version to the current version.
+    /// Fields introduced after version 1 default to their zero encoding.
+    pub fn migrate(&mut self, receiver_address: &Pubkey) {
+        if self.version == 0 {
+            // The wallet owns the receiver accounts created before the authority field
+            self.authority = *receiver_address;
+        }
+
+        self.version = Self::VERSION;
     }
 
     /// Get program-derived account address and bump seeds for the receiver
@@ -54,35 +100,580 @@ impl Receiver {
     }
 }
 
+impl Default for Receiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Policy deciding which senders can send documents to the receiver
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AccessPolicy {
+    /// Every sender can send documents
+    Open,
+    /// Only allowed senders can send documents
+    Allowlist,
+    /// Every sender except the blocked ones can send documents
+    Blocklist,
+}
+
+impl AccessPolicy {
+    /// Check if the sender with the given access entry can send documents
+    pub fn allows(&self, sender_access: Option<SenderAccess>) -> bool {
+        match self {
+            AccessPolicy::Open => true,
+            AccessPolicy::Allowlist => sender_access == Some(SenderAccess::Allowed),
+            AccessPolicy::Blocklist => sender_access != Some(SenderAccess::Blocked),
+        }
+    }
+}
+
+/// Access of a single sender set by the receiver
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SenderAccess {
+    /// Sender is on the allowlist of the receiver
+    Allowed,
+    /// Sender is on the blocklist of the receiver
+    Blocked,
+}
+
+/// Define a sender access account structure of the receiver
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct SenderAccessEntry {
+    /// Access of the sender
+    pub access: SenderAccess,
+}
+
+impl SenderAccessEntry {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "access";
+
+    /// Create a new sender access account
+    pub fn new(access: SenderAccess) -> Self {
+        Self {
+            access,
+        }
+    }
+
+    /// Get size of sender access account
+    pub fn retrieve_size() -> usize {
+        Self::new(SenderAccess::Allowed).try_to_vec().unwrap().len()
+    }
+
+    /// Get program-derived account address and bump seeds for the access of the sender
+    /// to the receiver
+    pub fn find_pda_address_with_bump_seed(
+        receiver_address: &Pubkey,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &receiver_address.to_bytes(),
+                &sender_address.to_bytes(),
+                SenderAccessEntry::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get program-derived account address for the access of the sender to the receiver
+    pub fn find_pda_address(
+        receiver_address: &Pubkey,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(receiver_address, sender_address, program_id).0
+    }
+}
+
+/// Define a delegate account structure of a key acting on behalf of the receiver
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct Delegate {
+    /// Timestamp from which the delegate can no longer act, none if it never expires
+    pub expires_at: Option<UnixTimestamp>,
+}
+
+impl Delegate {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "delegate";
+
+    /// Create a new delegate account
+    pub fn new(expires_at: Option<UnixTimestamp>) -> Self {
+        Self {
+            expires_at,
+        }
+    }
+
+    /// Get size of delegate account
+    pub fn retrieve_size() -> usize {
+        Self::new(Some(0)).try_to_vec().unwrap().len()
+    }
+
+    /// Check if the delegate can act at the given time
+    pub fn is_active(&self, now: UnixTimestamp) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now < expires_at,
+            None => true,
+        }
+    }
+
+    /// Get program-derived account address and bump seeds for the delegate of the receiver
+    pub fn find_pda_address_with_bump_seed(
+        receiver_address: &Pubkey,
+        delegate_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &receiver_address.to_bytes(),
+                &delegate_address.to_bytes(),
+                Delegate::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get program-derived account address for the delegate of the receiver
+    pub fn find_pda_address(
+        receiver_address: &Pubkey,
+        delegate_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(receiver_address, delegate_address, program_id).0
+    }
+}
+
+/// Define a sender outbox account structure
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Outbox {
+    /// Number of sent documents
+    pub documents_counter: u32,
+}
+
+impl Outbox {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "outbox";
+
+    /// Create a new dummy outbox account
+    pub fn new() -> Self {
+        Self {
+            documents_counter: 0,
+        }
+    }
+
+    /// Get size of outbox account
+    pub fn retrieve_size() -> usize {
+        mem::size_of::<Outbox>()
+    }
+
+    /// Get program-derived account address and bump seeds for the outbox of the sender
+    pub fn find_pda_address_with_bump_seed(
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &sender_address.to_bytes(),
+                Outbox::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get program-derived account address for the outbox of the sender
+    pub fn find_pda_address(
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(sender_address, program_id).0
+    }
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a sent document account structure referencing the document of the receiver
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct SentDocument {
+    /// Wallet address of the document receiver
+    pub receiver: Pubkey,
+    /// Index of the receiver's document
+    pub document_index: u32,
+}
+
+impl SentDocument {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "sent";
+
+    /// Create a new dummy sent document account
+    pub fn new() -> Self {
+        Self {
+            receiver: Pubkey::default(),
+            document_index: 0,
+        }
+    }
+
+    /// Get size of sent document account
+    pub fn retrieve_size() -> usize {
+        Self::new().try_to_vec().unwrap().len()
+    }
+
+    /// Get PDA address for the sent document of the sender and bump seeds
+    pub fn find_pda_address_with_bump_seed(
+        sent_document_index: u32,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &sender_address.to_bytes(),
+                (sent_document_index.to_string() + SentDocument::ACCOUNT_ADDRESS_SEED).as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get PDA address for the sent document of the sender
+    pub fn find_pda_address(
+        sent_document_index: u32,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(sent_document_index, sender_address, program_id).0
+    }
+}
+
+impl Default for SentDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a hash algorithm of the off-chain document digest
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Keccak256,
+    Blake2b256,
+}
+
+impl HashAlgorithm {
+    /// Get size of the digest calculated with the hash algorithm
+    pub fn digest_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha512 => 64,
+            _ => 32,
+        }
+    }
+}
+
+/// Define an encryption scheme of the off-chain document
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EncryptionScheme {
+    None,
+    X25519XSalsa20Poly1305,
+    X25519Aes256Gcm,
+}
+
+/// Define an encryption key of the receiver
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct EncryptionKey {
+    /// Encryption scheme of the key, none if the receiver did not publish a key
+    pub scheme: EncryptionScheme,
+    /// X25519 public key
+    pub public_key: [u8; 32],
+    /// Version of the key, incremented on every rotation, zero if no key was published
+    pub version: u32,
+}
+
+impl EncryptionKey {
+    /// Create an empty encryption key
+    pub fn new() -> Self {
+        Self {
+            scheme: EncryptionScheme::None,
+            public_key: [0; 32],
+            version: 0,
+        }
+    }
+}
+
+impl Default for EncryptionKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a document payload structure
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct DocumentPayload {
+    /// Version of the payload structure
+    pub version: u8,
+    /// Link of the off-chain meta file
+    pub uri: String,
+    /// Hash algorithm used to calculate the digest
+    pub hash_algorithm: HashAlgorithm,
+    /// Digest of the off-chain document
+    pub digest: Vec<u8>,
+    /// Mime type of the off-chain document
+    pub mime_type: String,
+    /// Size of the off-chain document in bytes
+    pub size: u64,
+    /// Encryption scheme of the off-chain document
+    pub encryption_scheme: EncryptionScheme,
+    /// Timestamp from which the document can no longer be opened, none if it never expires
+    pub expires_at: Option<UnixTimestamp>,
+}
+
+impl DocumentPayload {
+    pub const VERSION: u8 = 2;
+    pub const MAX_URI_LENGTH: usize = 256;
+    pub const MAX_MIME_TYPE_LENGTH: usize = 64;
+
+    /// Check if the payload is well-formed
+    pub fn validate(&self) -> Result<(), DocumentsError> {
+        if self.uri.len() > Self::MAX_URI_LENGTH
+            || self.mime_type.len() > Self::MAX_MIME_TYPE_LENGTH
+        {
+            return Err(DocumentsError::PayloadTooLarge);
+        }
+
+        if self.version != Self::VERSION
+            || self.uri.is_empty()
+            || self.digest.len() != self.hash_algorithm.digest_size()
+            || self.mime_type.is_empty()
+        {
+            return Err(DocumentsError::InvalidPayload);
+        }
+
+        Ok(())
+    }
+}
+
 /// Define a document account structure
+///
+/// The layout starts with its version so `unpack` can reject accounts it can not read.
+/// Document accounts of the unversioned layout, with the data stored as raw bytes, can
+/// not be read by this program, so it has to be deployed with a new program keypair on
+/// clusters holding them.
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
 pub struct Document {
+    /// Version of the account layout
+    pub version: u8,
     /// The sender of a document
     pub sender: Pubkey,
     /// Data about an off-chain document
-    pub data: Vec<u8>,
+    pub data: DocumentData,
     /// Timestamp at which the document was sent/received
     pub sent_at: UnixTimestamp,
     /// Timestamp at which the document was opened
     pub opened_at: UnixTimestamp,
+    /// Timestamp at which the sender recalled the document
+    pub revoked_at: UnixTimestamp,
+    /// Receipt of the receiver acknowledging or rejecting the document
+    pub receipt: Option<Receipt>,
+    /// Index of the document of the sender this document replies to
+    pub in_reply_to: Option<u32>,
+    /// Global ID of the document, see `compute_id`
+    pub id: [u8; 32],
+    /// Version of the receiver encryption key when the document was sent, zero if the
+    /// receiver did not publish a key
+    pub encryption_key_version: u32,
+    /// Original document the document was forwarded from, the sender is the forwarder
+    pub forwarded_from: Option<ForwardedFrom>,
+    /// Inbox state the receiver organizes the document by
+    pub receiver_metadata: ReceiverMetadata,
+    /// Timestamp at which the sender last amended the payload
+    pub amended_at: UnixTimestamp,
+    /// Number of times the sender amended the payload
+    pub amendments_count: u32,
+    /// Account that paid the rent of the document, the rent is returned to it on close
+    pub funder: Pubkey,
+    /// Index of the sent document account of the sender referencing the document
+    pub sent_document_index: u32,
+}
+
+/// Define the inbox state of a document, written only by the receiver
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ReceiverMetadata {
+    /// Whether the receiver archived the document
+    pub archived: bool,
+    /// Whether the receiver pinned the document
+    pub pinned: bool,
+    /// Bitset of the labels of the receiver, every bit is a label or folder defined by the clients
+    pub labels: u64,
+}
+
+impl ReceiverMetadata {
+    /// Create an empty inbox state
+    pub fn new() -> Self {
+        Self {
+            archived: false,
+            pinned: false,
+            labels: 0,
+        }
+    }
+}
+
+impl Default for ReceiverMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define the provenance of a forwarded document
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ForwardedFrom {
+    /// Wallet address of the receiver of the original document, the forwarder
+    pub receiver: Pubkey,
+    /// Index of the original document of the receiver
+    pub document_index: u32,
+    /// Sender of the original document
+    pub sender: Pubkey,
+    /// Timestamp at which the original document was sent
+    pub sent_at: UnixTimestamp,
+}
+
+/// Define a receipt the receiver issues for a document
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Receipt {
+    /// Whether the receiver acknowledged or rejected the document
+    pub status: ReceiptStatus,
+    /// Hash of the off-chain reason given by the receiver
+    pub reason_hash: Option<[u8; 32]>,
+    /// Timestamp at which the receiver issued the receipt
+    pub issued_at: UnixTimestamp,
+    /// Rent the receiver paid for the space of the receipt, returned to it on close
+    pub rent: u64,
+}
+
+/// Decision of the receiver about a document
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ReceiptStatus {
+    Acknowledged,
+    Rejected,
+}
+
+/// Data of a document, stored in the document account or shared by the receivers of a batch
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum DocumentData {
+    /// Payload stored in the document account
+    Payload(DocumentPayload),
+    /// Payload stored in the content account shared by the receivers of a batch
+    Content {
+        /// PDA address of the content account
+        address: Pubkey,
+        /// Expiry of the shared payload, copied to check the status of the document
+        expires_at: Option<UnixTimestamp>,
+    },
+}
+
+impl DocumentData {
+    /// Get the timestamp from which the document can no longer be opened
+    pub fn expires_at(&self) -> Option<UnixTimestamp> {
+        match self {
+            DocumentData::Payload(payload) => payload.expires_at,
+            DocumentData::Content { expires_at, .. } => *expires_at,
+        }
+    }
+}
+
+/// Status of a document at a point in time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocumentStatus {
+    /// Document can be opened by the receiver
+    Unopened,
+    /// Document was opened by the receiver
+    Opened,
+    /// Document was recalled by the sender before it was opened
+    Revoked,
+    /// Document expired before it was opened
+    Expired,
 }
 
 impl Document {
     pub const ACCOUNT_ADDRESS_SEED: &'static str = "document";
+    pub const VERSION: u8 = 1;
 
-    /// Create a new dummy document account
-    pub fn new(data_size: usize) -> Self {
+    /// Create a new document account with the given data
+    pub fn new(data: DocumentData) -> Self {
         Self {
+            version: Self::VERSION,
             sender: Pubkey::default(),
-            data: vec![0_u8; data_size],
+            data,
             sent_at: UnixTimestamp::default(),
             opened_at: UnixTimestamp::default(),
+            revoked_at: UnixTimestamp::default(),
+            receipt: None,
+            in_reply_to: None,
+            id: [0; 32],
+            encryption_key_version: 0,
+            forwarded_from: None,
+            receiver_metadata: ReceiverMetadata::new(),
+            amended_at: UnixTimestamp::default(),
+            amendments_count: 0,
+            funder: Pubkey::default(),
+            sent_document_index: 0,
+        }
+    }
+
+    /// Deserialize the document account, rejecting accounts of another layout version.
+    /// An unversioned account can start with the version byte as well, so the account
+    /// must also hold exactly one serialized document.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&Self::VERSION) {
+            return Err(DocumentsError::UnsupportedDocumentVersion.into());
+        }
+
+        let mut buffer = data;
+        let document = Self::deserialize(&mut buffer)
+            .map_err(|_| DocumentsError::UnsupportedDocumentVersion)?;
+        if !buffer.is_empty() {
+            return Err(DocumentsError::UnsupportedDocumentVersion.into());
+        }
+
+        Ok(document)
+    }
+
+    /// Get global ID of the document, the hash of its sender, receiver, index and data.
+    /// The document can be looked up by its ID through the document locator account.
+    pub fn compute_id(
+        sender_address: &Pubkey,
+        receiver_address: &Pubkey,
+        document_index: u32,
+        data: &DocumentData,
+    ) -> [u8; 32] {
+        hashv(&[
+            &sender_address.to_bytes(),
+            &receiver_address.to_bytes(),
+            &document_index.to_le_bytes(),
+            &data.try_to_vec().unwrap(),
+        ]).to_bytes()
+    }
+
+    /// Get status of the document at the given time
+    pub fn status(&self, now: UnixTimestamp) -> DocumentStatus {
+        if self.opened_at != 0 {
+            DocumentStatus::Opened
+        } else if self.revoked_at != 0 {
+            DocumentStatus::Revoked
+        } else if matches!(self.data.expires_at(), Some(expires_at) if now >= expires_at) {
+            DocumentStatus::Expired
+        } else {
+            DocumentStatus::Unopened
         }
     }
 
     /// Get size of document account
-    pub fn retrieve_size(data_size: usize) -> usize {
-        Self::new(data_size).try_to_vec().unwrap().len()
+    pub fn retrieve_size(
+        data: &DocumentData,
+        in_reply_to: Option<u32>,
+        forwarded_from: Option<ForwardedFrom>,
+    ) -> usize {
+        let mut document = Self::new(data.clone());
+        document.in_reply_to = in_reply_to;
+        document.forwarded_from = forwarded_from;
+        document.try_to_vec().unwrap().len()
     }
 
     /// Get PDA address for the document of the receiver and bump seeds
@@ -109,3 +700,217 @@ impl Document {
         Self::find_pda_address_with_bump_seed(document_index, receiver_address, program_id).0
     }
 }
+
+/// Define a document locator account structure referencing the document with the global ID
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct DocumentLocator {
+    /// Wallet address of the document receiver
+    pub receiver: Pubkey,
+    /// Index of the receiver's document
+    pub document_index: u32,
+}
+
+impl DocumentLocator {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "locator";
+
+    /// Create a new dummy document locator account
+    pub fn new() -> Self {
+        Self {
+            receiver: Pubkey::default(),
+            document_index: 0,
+        }
+    }
+
+    /// Get size of document locator account
+    pub fn retrieve_size() -> usize {
+        Self::new().try_to_vec().unwrap().len()
+    }
+
+    /// Get PDA address for the document locator of the global document ID and bump seeds
+    pub fn find_pda_address_with_bump_seed(
+        document_id: &[u8; 32],
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                document_id,
+                DocumentLocator::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get PDA address for the document locator of the global document ID
+    pub fn find_pda_address(
+        document_id: &[u8; 32],
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(document_id, program_id).0
+    }
+}
+
+impl Default for DocumentLocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a key envelope account structure granting a party access to the document
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct KeyEnvelope {
+    /// The receiver or the sender of the document that granted the access
+    pub granted_by: Pubkey,
+    /// Symmetric key of the document wrapped to the encryption key of the grantee
+    pub wrapped_key: Vec<u8>,
+}
+
+impl KeyEnvelope {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "envelope";
+    pub const MAX_WRAPPED_KEY_LENGTH: usize = 128;
+
+    /// Create a new key envelope account with the given wrapped key
+    pub fn new(wrapped_key: Vec<u8>) -> Self {
+        Self {
+            granted_by: Pubkey::default(),
+            wrapped_key,
+        }
+    }
+
+    /// Get size of key envelope account
+    pub fn retrieve_size(wrapped_key: &[u8]) -> usize {
+        Self::new(wrapped_key.to_vec()).try_to_vec().unwrap().len()
+    }
+
+    /// Get PDA address for the key envelope of the grantee and bump seeds
+    pub fn find_pda_address_with_bump_seed(
+        document_id: &[u8; 32],
+        grantee_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                document_id,
+                &grantee_address.to_bytes(),
+                KeyEnvelope::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get PDA address for the key envelope of the grantee
+    pub fn find_pda_address(
+        document_id: &[u8; 32],
+        grantee_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(document_id, grantee_address, program_id).0
+    }
+}
+
+/// Define a content account structure holding the payload shared by the receivers of a batch
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct Content {
+    /// The sender of the documents
+    pub sender: Pubkey,
+    /// Data about an off-chain document
+    pub data: DocumentPayload,
+    /// Number of documents referencing the content, the content is closed with the last of them
+    pub receivers_count: u32,
+    /// Account that paid the rent of the content, the rent is returned to it on close
+    pub funder: Pubkey,
+}
+
+impl Content {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "content";
+
+    /// Create a new content account with the given payload
+    pub fn new(data: DocumentPayload) -> Self {
+        Self {
+            sender: Pubkey::default(),
+            data,
+            receivers_count: 0,
+            funder: Pubkey::default(),
+        }
+    }
+
+    /// Get size of content account
+    pub fn retrieve_size(data: &DocumentPayload) -> usize {
+        Self::new(data.clone()).try_to_vec().unwrap().len()
+    }
+
+    /// Get PDA address for the content of the sender and bump seeds. The content is indexed
+    /// by the sent document index of the first document of the batch.
+    pub fn find_pda_address_with_bump_seed(
+        sent_document_index: u32,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &sender_address.to_bytes(),
+                (sent_document_index.to_string() + Content::ACCOUNT_ADDRESS_SEED).as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get PDA address for the content of the sender
+    pub fn find_pda_address(
+        sent_document_index: u32,
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(sent_document_index, sender_address, program_id).0
+    }
+}
+
+/// Define a sender nonce account structure
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SenderNonce {
+    /// Nonce expected in the next pre-signed transaction of the sender
+    pub nonce: u64,
+}
+
+impl SenderNonce {
+    pub const ACCOUNT_ADDRESS_SEED: &'static str = "nonce";
+
+    /// Create a new sender nonce account
+    pub fn new() -> Self {
+        Self {
+            nonce: 0,
+        }
+    }
+
+    /// Get size of sender nonce account
+    pub fn retrieve_size() -> usize {
+        mem::size_of::<SenderNonce>()
+    }
+
+    /// Get program-derived account address and bump seeds for the sender nonce
+    pub fn find_pda_address_with_bump_seed(
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                &sender_address.to_bytes(),
+                SenderNonce::ACCOUNT_ADDRESS_SEED.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Get program-derived account address for the sender nonce
+    pub fn find_pda_address(
+        sender_address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Pubkey {
+        Self::find_pda_address_with_bump_seed(sender_address, program_id).0
+    }
+}
+
+impl Default for SenderNonce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
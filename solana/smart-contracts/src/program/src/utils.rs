@@ -1,14 +1,24 @@
 //! Account utility functions
 
+use crate::error::DocumentsError;
 use solana_program::{
     account_info::AccountInfo,
+    ed25519_program,
     entrypoint::ProgramResult,
-    program::{invoke_signed},
+    program::{invoke, invoke_signed},
     pubkey::Pubkey,
     rent::Rent,
     system_instruction,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
 };
 
+/// Start of the signature offsets in the Ed25519 program instruction data
+const ED25519_SIGNATURE_OFFSETS_START: usize = 2;
+/// Start of the data following the signature offsets of a single signature
+const ED25519_DATA_START: usize = ED25519_SIGNATURE_OFFSETS_START + 14;
+/// Instruction index referring to the Ed25519 program instruction itself
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
 /// Creates Program Derived Address for the given seeds
 pub fn create_pda_account<'a>(
     payer: &AccountInfo<'a>,
@@ -35,3 +45,130 @@ pub fn create_pda_account<'a>(
         &[new_pda_signer_seeds],
     )
 }
+
+/// Resizes the program account to the given size. The payer funds the rent of the extra
+/// space when the account grows and gets back the rent of the released space when it shrinks.
+pub fn resize_account<'a>(
+    payer: &AccountInfo<'a>,
+    rent: &Rent,
+    space: usize,
+    system_program: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+) -> ProgramResult {
+    if account.data_len() == space {
+        return Ok(());
+    }
+
+    let minimum_balance = rent.minimum_balance(space);
+    let account_lamports = account.lamports();
+
+    if minimum_balance > account_lamports {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, minimum_balance - account_lamports),
+            &[
+                payer.clone(),
+                account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    } else if account.data_len() > space {
+        transfer_lamports(account, payer, account_lamports - minimum_balance)?;
+    }
+
+    account.realloc(space, true)
+}
+
+/// Moves lamports from the program account to the destination account
+pub fn transfer_lamports(
+    account: &AccountInfo,
+    destination: &AccountInfo,
+    lamports: u64,
+) -> ProgramResult {
+    let account_lamports = account.lamports()
+        .checked_sub(lamports)
+        .ok_or(DocumentsError::Overflow)?;
+    let destination_lamports = destination.lamports()
+        .checked_add(lamports)
+        .ok_or(DocumentsError::Overflow)?;
+
+    **account.lamports.borrow_mut() = account_lamports;
+    **destination.lamports.borrow_mut() = destination_lamports;
+
+    Ok(())
+}
+
+/// Closes the program account, moving its lamports to the destination account. The data
+/// is wiped and truncated, so later instructions of the transaction see an empty account.
+pub fn close_pda_account(
+    account: &AccountInfo,
+    destination: &AccountInfo,
+) -> ProgramResult {
+    let destination_lamports = destination.lamports()
+        .checked_add(account.lamports())
+        .ok_or(DocumentsError::Overflow)?;
+
+    **destination.lamports.borrow_mut() = destination_lamports;
+    **account.lamports.borrow_mut() = 0;
+    account.data.borrow_mut().fill(0);
+
+    account.realloc(0, false)
+}
+
+/// Verifies that the instruction preceding the current one is an Ed25519 program
+/// instruction with the signature of the signer over the given message
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> ProgramResult {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+
+    if current_index == 0 {
+        return Err(DocumentsError::MissingEd25519Instruction.into());
+    }
+
+    let instruction = load_instruction_at_checked(
+        current_index as usize - 1,
+        instructions_sysvar,
+    )?;
+
+    if instruction.program_id != ed25519_program::id() {
+        return Err(DocumentsError::MissingEd25519Instruction.into());
+    }
+
+    let data = &instruction.data;
+
+    // Only a single signature with the data stored in the instruction itself is accepted,
+    // since the runtime verifies the signature against the referenced instruction data
+    if data.len() < ED25519_DATA_START || data[0] != 1 {
+        return Err(DocumentsError::InvalidEd25519Instruction.into());
+    }
+
+    let read_offset = |position: usize| {
+        let start = ED25519_SIGNATURE_OFFSETS_START + position * 2;
+        u16::from_le_bytes([data[start], data[start + 1]])
+    };
+
+    let signature_instruction_index = read_offset(1);
+    let public_key_offset = read_offset(2) as usize;
+    let public_key_instruction_index = read_offset(3);
+    let message_data_offset = read_offset(4) as usize;
+    let message_data_size = read_offset(5) as usize;
+    let message_instruction_index = read_offset(6);
+
+    if signature_instruction_index != ED25519_CURRENT_INSTRUCTION
+        || public_key_instruction_index != ED25519_CURRENT_INSTRUCTION
+        || message_instruction_index != ED25519_CURRENT_INSTRUCTION
+    {
+        return Err(DocumentsError::InvalidEd25519Instruction.into());
+    }
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_data_offset..message_data_offset + message_data_size);
+
+    if public_key != Some(signer.as_ref()) || signed_message != Some(message) {
+        return Err(DocumentsError::InvalidSignature.into());
+    }
+
+    Ok(())
+}
@@ -4,25 +4,59 @@
 mod program_test;
 
 use {
-    borsh::{BorshDeserialize},
+    borsh::{BorshDeserialize, BorshSerialize},
     documents::{
+        error::DocumentsError,
         id,
         instruction::{
+            acknowledge_document,
+            add_delegate,
+            amend_document,
+            close_document,
+            close_key_envelope,
             create_receiver_account,
+            forward_document,
+            grant_document_access,
+            mark_document_opened,
+            mark_document_opened_by_delegate,
+            pre_signed_document_message,
+            recall_document,
+            reject_document,
+            remove_delegate,
+            revoke_document_access,
             send_document,
+            send_document_to_many,
+            send_funded_document,
+            send_pre_signed_document,
+            migrate_receiver,
+            set_encryption_key,
+            set_receiver_metadata,
+            set_sender_access,
+            update_receiver,
+        },
+        state::{
+            AccessPolicy, Content, Document, DocumentData, DocumentLocator, DocumentPayload, DocumentStatus, EncryptionKey, EncryptionScheme, ForwardedFrom, HashAlgorithm, KeyEnvelope, Outbox, Receipt,
+            ReceiptStatus, Receiver, ReceiverMetadata,
+            SenderAccess, SenderAccessEntry, SenderNonce, SentDocument,
         },
-        state::{Document, Receiver},
     },
     solana_program::{
+        instruction::InstructionError,
         pubkey::Pubkey,
+        rent::Rent,
         sysvar,
     },
     solana_program_test::*,
     solana_sdk::{
+        account::Account,
+        ed25519_instruction::new_ed25519_instruction,
+        native_token::LAMPORTS_PER_SOL,
         signature::{
+            Keypair,
             Signer,
         },
-        transaction::Transaction,
+        system_instruction,
+        transaction::{Transaction, TransactionError},
     },
     program_test::program_test,
 };
@@ -85,7 +119,7 @@ async fn test_create_receiver_account() {
     let is_rent_exempt = sysvar::rent::Rent::default()
         .is_exempt(receiver_pda_account.lamports, receiver_pda_account.data.len());
 
-    assert_eq!(is_rent_exempt, true);
+    assert!(is_rent_exempt);
 }
 
 #[tokio::test]
@@ -125,7 +159,10 @@ async fn test_send_document() {
                 &payer.pubkey(),
                 &receiver_wallet_address,
                 i as u32,
+                i as u32,
                 document_data.clone(),
+                None,
+                0,
             )],
             Some(&payer.pubkey()),
             &[&payer],
@@ -156,16 +193,52 @@ async fn test_send_document() {
             .unwrap();
 
         assert_eq!(document.sender, payer.pubkey());
-        assert_eq!(document.data, document_data.clone());
+        assert_eq!(document.data, DocumentData::Payload(document_data.clone()));
         assert_ne!(document.sent_at, 0);
         assert_eq!(document.opened_at, 0);
 
         let is_rent_exempt = sysvar::rent::Rent::default()
             .is_exempt(document_pda_account.lamports, document_pda_account.data.len());
 
-        assert_eq!(is_rent_exempt, true);
+        assert!(is_rent_exempt);
+    }
+
+    // Sender outbox should reference every document
+    for i in 0..documents_data.len() {
+        let sent_document_pda_address = SentDocument::find_pda_address(
+            i as u32,
+            &payer.pubkey(),
+            &id(),
+        );
+
+        let sent_document_pda_account = banks_client
+            .get_account(sent_document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("sent_document_pda_account not found");
+
+        assert_eq!(
+            SentDocument::try_from_slice(&sent_document_pda_account.data).unwrap(),
+            SentDocument {
+                receiver: receiver_wallet_address,
+                document_index: i as u32,
+            },
+        );
     }
 
+    let outbox_pda_account = banks_client
+        .get_account(Outbox::find_pda_address(&payer.pubkey(), &id()))
+        .await
+        .expect("get_account")
+        .expect("outbox_pda_account not found");
+
+    assert_eq!(
+        Outbox::try_from_slice(&outbox_pda_account.data)
+            .unwrap()
+            .documents_counter,
+        2,
+    );
+
     // Receiver PDA account should have 2 documents
     let receiver_pda_account = banks_client
         .get_account(receiver_pda_address)
@@ -181,12 +254,2824 @@ async fn test_send_document() {
     );
 }
 
-fn get_documents_dummy_data() -> Vec<Vec<u8>> {
-    let documents_data = vec![
-        String::from("0x18747470733a2f2f656d6e3137382e6769746875622e696f2f6f6e6c696e652d746f6f6c732f7368613235362e68746d6ce2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d197467165c9")
-            .into_bytes(),
-        String::from("0x28747470733a2f2f656d6e3137382e6769746875622e696f2f6f6e6c696e652d746f6f6c732f7368613235362e68746d6ce2c1fcbd5b4befacb2ebdc5a7b6e6da86ad5b2a1ebb50371a546d19746")
-            .into_bytes(),
+#[tokio::test]
+async fn test_send_document_to_many() {
+    let receiver_wallet_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Create Receiver PDA accounts
+    for receiver_wallet_address in receiver_wallet_addresses.iter() {
+        let transaction = Transaction::new_signed_with_payer(
+            &[create_receiver_account(
+                &payer.pubkey(),
+                receiver_wallet_address,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        );
+
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    let document_data = get_documents_dummy_data()[0].clone();
+    let receivers: Vec<(Pubkey, u32, u32)> = receiver_wallet_addresses
+        .iter()
+        .map(|receiver_wallet_address| (*receiver_wallet_address, 0, 0))
+        .collect();
+
+    // Send one document to both receivers
+    let transaction = Transaction::new_signed_with_payer(
+        &[send_document_to_many(
+            &payer.pubkey(),
+            &payer.pubkey(),
+            &receivers,
+            0,
+            document_data.clone(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Payload is stored once in the shared content account
+    let content_pda_address = Content::find_pda_address(
+        0,
+        &payer.pubkey(),
+        &id(),
+    );
+
+    let content_pda_account = banks_client
+        .get_account(content_pda_address)
+        .await
+        .expect("get_account")
+        .expect("content_pda_account not found");
+
+    assert_eq!(
+        Content::try_from_slice(&content_pda_account.data).unwrap(),
+        Content {
+            sender: payer.pubkey(),
+            data: document_data.clone(),
+            receivers_count: 2,
+            funder: payer.pubkey(),
+        },
+    );
+
+    // Every receiver gets a document referencing the content
+    for (i, receiver_wallet_address) in receiver_wallet_addresses.iter().enumerate() {
+        let document_pda_address = Document::find_pda_address(
+            0,
+            receiver_wallet_address,
+            &id(),
+        );
+
+        let document_pda_account = banks_client
+            .get_account(document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("document_pda_account not found");
+
+        let document = Document::try_from_slice(&document_pda_account.data)
+            .unwrap();
+
+        assert_eq!(document.sender, payer.pubkey());
+        assert_eq!(
+            document.data,
+            DocumentData::Content {
+                address: content_pda_address,
+                expires_at: None,
+            },
+        );
+        assert_eq!(document.status(document.sent_at), DocumentStatus::Unopened);
+
+        let sent_document_pda_address = SentDocument::find_pda_address(
+            i as u32,
+            &payer.pubkey(),
+            &id(),
+        );
+
+        let sent_document_pda_account = banks_client
+            .get_account(sent_document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("sent_document_pda_account not found");
+
+        assert_eq!(
+            SentDocument::try_from_slice(&sent_document_pda_account.data).unwrap(),
+            SentDocument {
+                receiver: *receiver_wallet_address,
+                document_index: 0,
+            },
+        );
+    }
+
+    // Sending the same batch index again must fail
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[send_document_to_many(
+            &payer.pubkey(),
+            &payer.pubkey(),
+            &[(receiver_wallet_addresses[0], 1, 0)],
+            0,
+            document_data,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::ContentAlreadyExists as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_close_batch_document() {
+    let receiver_wallet = Keypair::new();
+    let other_receiver_wallet = Keypair::new();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    let content_pda_address = Content::find_pda_address(
+        0,
+        &payer.pubkey(),
+        &id(),
+    );
+    let content_data = DocumentData::Content {
+        address: content_pda_address,
+        expires_at: None,
+    };
+
+    // Send one document to both receivers, the receiver forwards its copy to the other one
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &other_receiver_wallet.pubkey(),
+            ),
+            send_document_to_many(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &[(receiver_wallet.pubkey(), 0, 0), (other_receiver_wallet.pubkey(), 0, 0)],
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+            ),
+            forward_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &content_data,
+                &other_receiver_wallet.pubkey(),
+                1,
+                0,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut documents = Vec::new();
+
+    for (wallet_address, document_index) in [
+        (receiver_wallet.pubkey(), 0),
+        (other_receiver_wallet.pubkey(), 0),
+        (other_receiver_wallet.pubkey(), 1),
+    ] {
+        let document_pda_account = banks_client
+            .get_account(Document::find_pda_address(document_index, &wallet_address, &id()))
+            .await
+            .expect("get_account")
+            .expect("document_pda_account not found");
+
+        let document = Document::try_from_slice(&document_pda_account.data).unwrap();
+
+        assert_eq!(document.data, content_data);
+
+        documents.push(document);
+    }
+
+    // Forwarded document references the content as well
+    let content_pda_account = banks_client
+        .get_account(content_pda_address)
+        .await
+        .expect("get_account")
+        .expect("content_pda_account not found");
+
+    assert_eq!(Content::try_from_slice(&content_pda_account.data).unwrap().receivers_count, 3);
+
+    // Closing a document releases the content
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+            close_document(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &documents[0],
+                Some(&payer.pubkey()),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let content_pda_account = banks_client
+        .get_account(content_pda_address)
+        .await
+        .expect("get_account")
+        .expect("content_pda_account not found");
+
+    assert_eq!(Content::try_from_slice(&content_pda_account.data).unwrap().receivers_count, 2);
+
+    // Content is closed with the last document referencing it
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &other_receiver_wallet.pubkey(),
+                0,
+            ),
+            close_document(
+                &other_receiver_wallet.pubkey(),
+                &other_receiver_wallet.pubkey(),
+                0,
+                &documents[1],
+                Some(&payer.pubkey()),
+            ),
+            mark_document_opened(
+                &other_receiver_wallet.pubkey(),
+                1,
+            ),
+            close_document(
+                &other_receiver_wallet.pubkey(),
+                &other_receiver_wallet.pubkey(),
+                1,
+                &documents[2],
+                Some(&payer.pubkey()),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &other_receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        banks_client
+            .get_account(content_pda_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+}
+
+#[tokio::test]
+async fn test_send_reply() {
+    let receiver_wallet = Keypair::new();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver gets a document and replies to its sender
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &payer.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            send_funded_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                &payer.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[1].clone(),
+                Some(0),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let reply_pda_address = Document::find_pda_address(
+        0,
+        &payer.pubkey(),
+        &id(),
+    );
+
+    let reply_pda_account = banks_client
+        .get_account(reply_pda_address)
+        .await
+        .expect("get_account")
+        .expect("reply_pda_account not found");
+
+    let reply: Document = Document::try_from_slice(&reply_pda_account.data)
+        .unwrap();
+
+    assert_eq!(reply.sender, receiver_wallet.pubkey());
+    assert_eq!(reply.in_reply_to, Some(0));
+
+    // Only documents the replier received can be replied to
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            send_funded_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                &payer.pubkey(),
+                1,
+                1,
+                get_documents_dummy_data()[1].clone(),
+                Some(1),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentNotFound as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_forward_document() {
+    let receiver_wallet = Keypair::new();
+    let other_receiver_wallet = Keypair::new();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver gets a document and forwards it to the other receiver
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &other_receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            forward_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &DocumentData::Payload(get_documents_dummy_data()[0].clone()),
+                &other_receiver_wallet.pubkey(),
+                0,
+                0,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let original_pda_account = banks_client
+        .get_account(Document::find_pda_address(0, &receiver_wallet.pubkey(), &id()))
+        .await
+        .expect("get_account")
+        .expect("original_pda_account not found");
+
+    let original: Document = Document::try_from_slice(&original_pda_account.data)
+        .unwrap();
+
+    let forwarded_pda_account = banks_client
+        .get_account(Document::find_pda_address(0, &other_receiver_wallet.pubkey(), &id()))
+        .await
+        .expect("get_account")
+        .expect("forwarded_pda_account not found");
+
+    let forwarded: Document = Document::try_from_slice(&forwarded_pda_account.data)
+        .unwrap();
+
+    assert_eq!(forwarded.sender, receiver_wallet.pubkey());
+    assert_eq!(forwarded.data, original.data);
+    assert_eq!(
+        forwarded.forwarded_from,
+        Some(ForwardedFrom {
+            receiver: receiver_wallet.pubkey(),
+            document_index: 0,
+            sender: payer.pubkey(),
+            sent_at: original.sent_at,
+        }),
+    );
+
+    let sent_document_pda_account = banks_client
+        .get_account(SentDocument::find_pda_address(0, &receiver_wallet.pubkey(), &id()))
+        .await
+        .expect("get_account")
+        .expect("sent_document_pda_account not found");
+
+    assert_eq!(
+        SentDocument::try_from_slice(&sent_document_pda_account.data).unwrap(),
+        SentDocument {
+            receiver: other_receiver_wallet.pubkey(),
+            document_index: 0,
+        },
+    );
+
+    // Only documents the forwarder received can be forwarded
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            forward_document(
+                &payer.pubkey(),
+                &other_receiver_wallet.pubkey(),
+                1,
+                &DocumentData::Payload(get_documents_dummy_data()[0].clone()),
+                &receiver_wallet.pubkey(),
+                1,
+                0,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &other_receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentNotFound as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_send_invalid_document() {
+    let receiver_wallet_address = Pubkey::new_unique();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Digest does not match the hash algorithm
+    let mut document_data = get_documents_dummy_data()[0].clone();
+    document_data.digest.pop();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+                0,
+                0,
+                document_data,
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DocumentsError::InvalidPayload as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_send_funded_document() {
+    let sender_wallet = Keypair::new();
+    let receiver_wallet_address = Pubkey::new_unique();
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet_address,
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Payer funds the document authored by the sender
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+            ),
+            send_funded_document(
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                &receiver_wallet_address,
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &sender_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_eq!(document.sender, sender_wallet.pubkey());
+}
+
+#[tokio::test]
+async fn test_sender_access_policy() {
+    let sender_wallet = Keypair::new();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    let receiver_pda_address = Receiver::find_pda_address(
+        &payer.pubkey(),
+        &id(),
+    );
+    let sender_access_pda_address = SenderAccessEntry::find_pda_address(
+        &payer.pubkey(),
+        &sender_wallet.pubkey(),
+        &id(),
+    );
+
+    // Receiver accepts documents only from allowed senders
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &payer.pubkey(),
+            ),
+            update_receiver(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                None,
+                Some(AccessPolicy::Allowlist),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let send_transaction = |document_index: u32, recent_blockhash| {
+        Transaction::new_signed_with_payer(
+            &[
+                send_funded_document(
+                    &payer.pubkey(),
+                    &sender_wallet.pubkey(),
+                    &payer.pubkey(),
+                    document_index,
+                    document_index,
+                    get_documents_dummy_data()[0].clone(),
+                    None,
+                    0,
+                ),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer, &sender_wallet],
+            recent_blockhash,
+        )
+    };
+    let sender_not_allowed = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(DocumentsError::SenderNotAllowed as u32),
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(send_transaction(0, recent_blockhash)).await.unwrap_err().unwrap(),
+        sender_not_allowed,
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            set_sender_access(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                Some(SenderAccess::Allowed),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+
+    banks_client
+        .process_transaction(send_transaction(0, recent_blockhash))
+        .await
+        .unwrap();
+
+    // Receiver accepts documents from every sender except the blocked ones
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            update_receiver(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                None,
+                Some(AccessPolicy::Blocklist),
+                None,
+            ),
+            set_sender_access(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                Some(SenderAccess::Blocked),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        banks_client.process_transaction(send_transaction(1, recent_blockhash)).await.unwrap_err().unwrap(),
+        sender_not_allowed,
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            set_sender_access(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+
+    banks_client
+        .process_transaction(send_transaction(1, recent_blockhash))
+        .await
+        .unwrap();
+
+    let receiver_pda_account = banks_client
+        .get_account(receiver_pda_address)
+        .await
+        .expect("get_account")
+        .expect("receiver_pda_account not found");
+
+    let receiver: Receiver = Receiver::try_from_slice(&receiver_pda_account.data)
+        .unwrap();
+
+    assert_eq!(receiver.documents_counter, 2);
+    assert_eq!(receiver.access_policy, AccessPolicy::Blocklist);
+    assert!(banks_client.get_account(sender_access_pda_address).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_update_receiver() {
+    let receiver_wallet = Keypair::new();
+    let authority = Keypair::new();
+    let receiver_pda_address = Receiver::find_pda_address(
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver wallet is the initial authority and hands it over
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            update_receiver(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                Some(authority.pubkey()),
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Receiver wallet is not the authority anymore
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            update_receiver(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                None,
+                Some(AccessPolicy::Allowlist),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::InvalidAuthority as u32),
+        ),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            update_receiver(
+                &authority.pubkey(),
+                &receiver_wallet.pubkey(),
+                None,
+                Some(AccessPolicy::Allowlist),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let receiver_pda_account = banks_client
+        .get_account(receiver_pda_address)
+        .await
+        .expect("get_account")
+        .expect("receiver_pda_account not found");
+
+    let receiver: Receiver = Receiver::try_from_slice(&receiver_pda_account.data)
+        .unwrap();
+
+    assert_eq!(receiver.version, Receiver::VERSION);
+    assert_eq!(receiver.authority, authority.pubkey());
+    assert_eq!(receiver.access_policy, AccessPolicy::Allowlist);
+}
+
+#[tokio::test]
+async fn test_send_document_with_postage() {
+    let receiver_wallet = Keypair::new();
+    let postage = 1_000_000_000;
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver requires postage and the payer pays it when sending the document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            update_receiver(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                None,
+                None,
+                Some(postage),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(receiver_wallet.pubkey()).await.unwrap(),
+        postage,
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_receiver() {
+    let receiver_wallet_address = Pubkey::new_unique();
+    let receiver_pda_address = Receiver::find_pda_address(
+        &receiver_wallet_address,
+        &id(),
+    );
+
+    // Receiver account created before the access policy and the authority were added
+    let mut program_test = program_test();
+    program_test.add_account(
+        receiver_pda_address,
+        Account {
+            lamports: Rent::default().minimum_balance(4),
+            data: 7_u32.to_le_bytes().to_vec(),
+            owner: id(),
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test.start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            migrate_receiver(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let receiver_pda_account = banks_client
+        .get_account(receiver_pda_address)
+        .await
+        .expect("get_account")
+        .expect("receiver_pda_account not found");
+
+    let receiver: Receiver = Receiver::try_from_slice(&receiver_pda_account.data)
+        .unwrap();
+
+    assert_eq!(receiver.documents_counter, 7);
+    assert_eq!(receiver.access_policy, AccessPolicy::Open);
+    assert_eq!(receiver.version, Receiver::VERSION);
+    assert_eq!(receiver.authority, receiver_wallet_address);
+    assert_eq!(receiver.postage, 0);
+    assert_eq!(receiver.encryption_key, EncryptionKey::new());
+    assert!(Rent::default().is_exempt(receiver_pda_account.lamports, receiver_pda_account.data.len()));
+}
+
+#[tokio::test]
+async fn test_open_unversioned_document() {
+    let receiver_wallet = Keypair::new();
+    let mut program_test = program_test();
+
+    // Document accounts created before the layout was versioned, with the data stored as raw
+    // bytes. The sender of the second one starts with the byte of the current version.
+    for (index, sender) in [[2; 32], [Document::VERSION; 32]].iter().enumerate() {
+        let document_pda_address = Document::find_pda_address(
+            index as u32,
+            &receiver_wallet.pubkey(),
+            &id(),
+        );
+        let document_data = (
+            Pubkey::new_from_array(*sender),
+            b"https://example.com/document.pdf".to_vec(),
+            1_i64,
+            0_i64,
+        ).try_to_vec().unwrap();
+
+        program_test.add_account(
+            document_pda_address,
+            Account {
+                lamports: Rent::default().minimum_balance(document_data.len()),
+                data: document_data,
+                owner: id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test.start().await;
+
+    for index in 0..2 {
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                mark_document_opened(
+                    &receiver_wallet.pubkey(),
+                    index,
+                ),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer, &receiver_wallet],
+            recent_blockhash,
+        );
+
+        assert_eq!(
+            banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(DocumentsError::UnsupportedDocumentVersion as u32),
+            ),
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_set_encryption_key() {
+    let receiver_wallet = Keypair::new();
+    let receiver_pda_address = Receiver::find_pda_address(
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Documents record the key version of the receiver they are encrypted to
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    for (document_index, (scheme, public_key)) in [
+        (EncryptionScheme::X25519XSalsa20Poly1305, [1; 32]),
+        (EncryptionScheme::X25519Aes256Gcm, [2; 32]),
+    ].iter().enumerate() {
+        let document_index = document_index as u32 + 1;
+
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                set_encryption_key(
+                    &receiver_wallet.pubkey(),
+                    &receiver_wallet.pubkey(),
+                    *scheme,
+                    *public_key,
+                ),
+                send_document(
+                    &payer.pubkey(),
+                    &receiver_wallet.pubkey(),
+                    document_index,
+                    document_index,
+                    get_documents_dummy_data()[0].clone(),
+                    None,
+                    document_index,
+                ),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer, &receiver_wallet],
+            recent_blockhash,
+        );
+
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    let receiver_pda_account = banks_client
+        .get_account(receiver_pda_address)
+        .await
+        .expect("get_account")
+        .expect("receiver_pda_account not found");
+
+    assert_eq!(
+        Receiver::try_from_slice(&receiver_pda_account.data).unwrap().encryption_key,
+        EncryptionKey {
+            scheme: EncryptionScheme::X25519Aes256Gcm,
+            public_key: [2; 32],
+            version: 2,
+        },
+    );
+
+    for document_index in 0..3 {
+        let document_pda_address = Document::find_pda_address(
+            document_index,
+            &receiver_wallet.pubkey(),
+            &id(),
+        );
+
+        let document_pda_account = banks_client
+            .get_account(document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("document_pda_account not found");
+
+        assert_eq!(
+            Document::try_from_slice(&document_pda_account.data).unwrap().encryption_key_version,
+            document_index,
+        );
+    }
+
+    // Document encrypted to a rotated key is rejected
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                3,
+                3,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                1,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::EncryptionKeyVersionMismatch as u32),
+        ),
+    );
+
+    // Encryption key must have an encryption scheme
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            set_encryption_key(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                EncryptionScheme::None,
+                [3; 32],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::InvalidEncryptionKey as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_mark_document_opened() {
+    let receiver_wallet = Keypair::new();
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Create Receiver PDA account and send a document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Only the receiver can mark the document as opened
+    let transaction = Transaction::new_signed_with_payer(
+        &[mark_document_opened(
+            &payer.pubkey(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Mark document as opened
+    let transaction = Transaction::new_signed_with_payer(
+        &[mark_document_opened(
+            &receiver_wallet.pubkey(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_ne!(document.opened_at, 0);
+
+    // Document can not be opened twice
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[mark_document_opened(
+            &receiver_wallet.pubkey(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_mark_document_opened_by_delegate() {
+    let delegate_wallet = Keypair::new();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &payer.pubkey(),
+        &id(),
+    );
+
+    // Receiver authorizes the delegate without expiry
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &payer.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            send_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                1,
+                1,
+                get_documents_dummy_data()[1].clone(),
+                None,
+                0,
+            ),
+            add_delegate(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &delegate_wallet.pubkey(),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened_by_delegate(
+                &delegate_wallet.pubkey(),
+                &payer.pubkey(),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_ne!(document.opened_at, 0);
+
+    // Expired delegate can not open documents
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            add_delegate(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &delegate_wallet.pubkey(),
+                Some(1),
+            ),
+            mark_document_opened_by_delegate(
+                &delegate_wallet.pubkey(),
+                &payer.pubkey(),
+                1,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DocumentsError::DelegateExpired as u32),
+        ),
+    );
+
+    // Removed delegate can not open documents
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            remove_delegate(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &delegate_wallet.pubkey(),
+            ),
+            mark_document_opened_by_delegate(
+                &delegate_wallet.pubkey(),
+                &payer.pubkey(),
+                1,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DocumentsError::DelegateNotFound as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_recall_document() {
+    let receiver_wallet = Keypair::new();
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Documents that already expired can not be sent
+    let mut expired_document_data = get_documents_dummy_data()[0].clone();
+    expired_document_data.expires_at = Some(1);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                expired_document_data,
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DocumentsError::DocumentExpired as u32),
+        ),
+    );
+
+    // Sender recalls the document before the receiver opens it
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            recall_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_ne!(document.revoked_at, 0);
+    assert_eq!(document.status(document.revoked_at), DocumentStatus::Revoked);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentRevoked as u32),
+        ),
+    );
+
+    // Sender reclaims the rent of the recalled document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            close_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert!(banks_client.get_account(document_pda_address).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_amend_document() {
+    let receiver_wallet = Keypair::new();
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Sender amends the unopened document with a larger payload
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            amend_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                get_documents_dummy_data()[1].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_eq!(document.data, DocumentData::Payload(get_documents_dummy_data()[1].clone()));
+    assert_eq!(document.amendments_count, 1);
+    assert_ne!(document.amended_at, 0);
+    assert!(Rent::default().is_exempt(document_pda_account.lamports, document_pda_account.data.len()));
+
+    // The document account shrinks back with a smaller payload
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            amend_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                get_documents_dummy_data()[0].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_eq!(document.data, DocumentData::Payload(get_documents_dummy_data()[0].clone()));
+    assert_eq!(document.amendments_count, 2);
+
+    // The rent of the released space is returned to the funder
+    assert_eq!(
+        document_pda_account.lamports,
+        Rent::default().minimum_balance(document_pda_account.data.len()),
+    );
+
+    // Only the funder of the document can fund the amendment
+    let other_funder = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            amend_document(
+                &other_funder.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                get_documents_dummy_data()[1].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &other_funder],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::NotFunder as u32),
+        ),
+    );
+
+    // Only the sender can amend the document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            amend_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                get_documents_dummy_data()[1].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::NotSender as u32),
+        ),
+    );
+
+    // Opened documents can no longer be amended
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+            amend_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                get_documents_dummy_data()[1].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DocumentsError::AlreadyOpened as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_amend_batch_document() {
+    let receiver_wallet_address = Pubkey::new_unique();
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Documents of a batch share their content with the other receivers
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+            ),
+            send_document_to_many(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &[(receiver_wallet_address, 0, 0)],
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+            ),
+            amend_document(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet_address,
+                0,
+                get_documents_dummy_data()[1].clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(DocumentsError::SharedContentNotAmendable as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_acknowledge_document() {
+    let receiver_wallet = Keypair::new();
+    let reason_hash = [7; 32];
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver pays the rent of the receipts
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                LAMPORTS_PER_SOL,
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                1,
+                1,
+                get_documents_dummy_data()[1].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut documents_lamports = Vec::new();
+
+    for i in 0..2 {
+        let document_pda_address = Document::find_pda_address(
+            i,
+            &receiver_wallet.pubkey(),
+            &id(),
+        );
+
+        let document_pda_account = banks_client
+            .get_account(document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("document_pda_account not found");
+
+        documents_lamports.push(document_pda_account.lamports);
+    }
+
+    // Receiver acknowledges the first document and rejects the second one
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            acknowledge_document(
+                &receiver_wallet.pubkey(),
+                0,
+                Some(reason_hash),
+            ),
+            reject_document(
+                &receiver_wallet.pubkey(),
+                1,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    for (i, (status, reason_hash)) in [
+        (ReceiptStatus::Acknowledged, Some(reason_hash)),
+        (ReceiptStatus::Rejected, None),
+    ].iter().enumerate() {
+        let document_pda_address = Document::find_pda_address(
+            i as u32,
+            &receiver_wallet.pubkey(),
+            &id(),
+        );
+
+        let document_pda_account = banks_client
+            .get_account(document_pda_address)
+            .await
+            .expect("get_account")
+            .expect("document_pda_account not found");
+
+        let document: Document = Document::try_from_slice(&document_pda_account.data)
+            .unwrap();
+
+        // Issuing the receipt opens the document and records the rent the receiver paid
+        let receipt_rent = document_pda_account.lamports - documents_lamports[i];
+
+        assert_ne!(document.opened_at, 0);
+        assert_ne!(receipt_rent, 0);
+        assert_eq!(
+            document.receipt,
+            Some(Receipt {
+                status: *status,
+                reason_hash: *reason_hash,
+                issued_at: document.opened_at,
+                rent: receipt_rent,
+            }),
+        );
+
+        let is_rent_exempt = Rent::default()
+            .is_exempt(document_pda_account.lamports, document_pda_account.data.len());
+
+        assert!(is_rent_exempt);
+    }
+
+    // The receipt can not be changed
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            acknowledge_document(
+                &receiver_wallet.pubkey(),
+                1,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::ReceiptAlreadyIssued as u32),
+        ),
+    );
+
+    // Closing the document returns the rent of the receipt to the receiver
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document = Document::try_from_slice(&document_pda_account.data).unwrap();
+    let receiver_lamports = banks_client.get_balance(receiver_wallet.pubkey()).await.unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_document(
+            &receiver_wallet.pubkey(),
+            &receiver_wallet.pubkey(),
+            0,
+            &document,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(receiver_wallet.pubkey()).await.unwrap(),
+        receiver_lamports + document.receipt.unwrap().rent,
+    );
+}
+
+#[tokio::test]
+async fn test_set_receiver_metadata() {
+    let receiver_wallet = Keypair::new();
+    let metadata = ReceiverMetadata {
+        archived: true,
+        pinned: false,
+        labels: 0b101,
+    };
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver archives the document and labels it
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+            set_receiver_metadata(
+                &receiver_wallet.pubkey(),
+                0,
+                metadata,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(Document::find_pda_address(0, &receiver_wallet.pubkey(), &id()))
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_eq!(document.receiver_metadata, metadata);
+
+    // Only the receiver's own documents can be organized
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            set_receiver_metadata(
+                &payer.pubkey(),
+                0,
+                metadata,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentNotFound as u32),
+        ),
+    );
+}
+
+#[tokio::test]
+async fn test_document_access() {
+    let receiver_wallet = Keypair::new();
+    let stranger = Keypair::new();
+    let auditor_address = Pubkey::new_unique();
+    let colleague_address = Pubkey::new_unique();
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    // Receiver pays the rent of the key envelopes it grants
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                LAMPORTS_PER_SOL,
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_document(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document_id = Document::try_from_slice(&document_pda_account.data)
+        .unwrap()
+        .id;
+
+    // Sender grants the auditor and receiver grants the colleague access to the document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            grant_document_access(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document_id,
+                &auditor_address,
+                vec![1; 48],
+            ),
+            grant_document_access(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document_id,
+                &colleague_address,
+                vec![2; 48],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    for (grantee_address, granted_by, wrapped_key) in [
+        (auditor_address, payer.pubkey(), vec![1; 48]),
+        (colleague_address, receiver_wallet.pubkey(), vec![2; 48]),
+    ].iter() {
+        let key_envelope_pda_account = banks_client
+            .get_account(KeyEnvelope::find_pda_address(&document_id, grantee_address, &id()))
+            .await
+            .expect("get_account")
+            .expect("key_envelope_pda_account not found");
+
+        assert_eq!(
+            KeyEnvelope::try_from_slice(&key_envelope_pda_account.data).unwrap(),
+            KeyEnvelope {
+                granted_by: *granted_by,
+                wrapped_key: wrapped_key.clone(),
+            },
+        );
+    }
+
+    // Only the receiver or the sender can grant access
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            grant_document_access(
+                &stranger.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document_id,
+                &stranger.pubkey(),
+                vec![3; 48],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &stranger],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::NotReceiverOrSender as u32),
+        ),
+    );
+
+    let receiver_balance = banks_client
+        .get_balance(receiver_wallet.pubkey())
+        .await
+        .unwrap();
+
+    // Receiver revokes the access granted by the sender, the rent goes back to the sender
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            revoke_document_access(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document_id,
+                &auditor_address,
+                &payer.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert!(banks_client
+        .get_account(KeyEnvelope::find_pda_address(&document_id, &auditor_address, &id()))
+        .await
+        .unwrap()
+        .is_none());
+
+    assert_eq!(
+        banks_client
+            .get_balance(receiver_wallet.pubkey())
+            .await
+            .unwrap(),
+        receiver_balance,
+    );
+
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            revoke_document_access(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document_id,
+                &auditor_address,
+                &payer.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::KeyEnvelopeNotFound as u32),
+        ),
+    );
+
+    // Key envelope of an open document can only be revoked
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            close_key_envelope(
+                &receiver_wallet.pubkey(),
+                &document_id,
+                &colleague_address,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentNotClosed as u32),
+        ),
+    );
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+    let colleague_key_envelope_pda_address = KeyEnvelope::find_pda_address(
+        &document_id,
+        &colleague_address,
+        &id(),
+    );
+    let key_envelope_lamports = banks_client
+        .get_balance(colleague_key_envelope_pda_address)
+        .await
+        .unwrap();
+    let receiver_balance = banks_client
+        .get_balance(receiver_wallet.pubkey())
+        .await
+        .unwrap();
+
+    // Receiver closes the document and then the key envelope it granted
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+            close_document(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document,
+                None,
+            ),
+            close_key_envelope(
+                &receiver_wallet.pubkey(),
+                &document_id,
+                &colleague_address,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    assert!(banks_client
+        .get_account(colleague_key_envelope_pda_address)
+        .await
+        .unwrap()
+        .is_none());
+
+    assert_eq!(
+        banks_client
+            .get_balance(receiver_wallet.pubkey())
+            .await
+            .unwrap(),
+        receiver_balance + key_envelope_lamports,
+    );
+}
+
+#[tokio::test]
+async fn test_send_pre_signed_document() {
+    let sender_wallet = Keypair::new();
+    let receiver_wallet_address = Pubkey::new_unique();
+    let sender_nonce_pda_address = SenderNonce::find_pda_address(
+        &sender_wallet.pubkey(),
+        &id(),
+    );
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet_address,
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    let document_data = get_documents_dummy_data()[0].clone();
+
+    // Sender signs the document off-chain
+    let sender_keypair = ed25519_dalek::Keypair::from_bytes(&sender_wallet.to_bytes())
+        .unwrap();
+    let signature_instruction = new_ed25519_instruction(
+        &sender_keypair,
+        &pre_signed_document_message(
+            &id(),
+            &sender_wallet.pubkey(),
+            &receiver_wallet_address,
+            &document_data,
+            0,
+            None,
+            0,
+        ),
+    );
+
+    // Relayer creates Receiver PDA account and submits the pre-signed document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet_address,
+            ),
+            signature_instruction.clone(),
+            send_pre_signed_document(
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                &receiver_wallet_address,
+                0,
+                0,
+                document_data.clone(),
+                0,
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document: Document = Document::try_from_slice(&document_pda_account.data)
+        .unwrap();
+
+    assert_eq!(document.sender, sender_wallet.pubkey());
+    assert_eq!(document.data, DocumentData::Payload(document_data.clone()));
+
+    let sender_nonce_pda_account = banks_client
+        .get_account(sender_nonce_pda_address)
+        .await
+        .expect("get_account")
+        .expect("sender_nonce_pda_account not found");
+
+    assert_eq!(
+        SenderNonce::try_from_slice(&sender_nonce_pda_account.data)
+            .unwrap()
+            .nonce,
+        1,
+    );
+
+    // Signed message can not be replayed
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            signature_instruction,
+            send_pre_signed_document(
+                &payer.pubkey(),
+                &sender_wallet.pubkey(),
+                &receiver_wallet_address,
+                1,
+                1,
+                document_data.clone(),
+                0,
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Document can not be sent without the signature of the sender
+    let transaction = Transaction::new_signed_with_payer(
+        &[send_pre_signed_document(
+            &payer.pubkey(),
+            &sender_wallet.pubkey(),
+            &receiver_wallet_address,
+            1,
+            1,
+            document_data,
+            1,
+            None,
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_close_document() {
+    let receiver_wallet = Keypair::new();
+    let funder = Keypair::new();
+    let receiver_pda_address = Receiver::find_pda_address(
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+    let document_pda_address = Document::find_pda_address(
+        0,
+        &receiver_wallet.pubkey(),
+        &id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test().start().await;
+
+    let document_id = Document::compute_id(
+        &payer.pubkey(),
+        &receiver_wallet.pubkey(),
+        0,
+        &DocumentData::Payload(get_documents_dummy_data()[0].clone()),
+    );
+    let document_locator_pda_address = DocumentLocator::find_pda_address(
+        &document_id,
+        &id(),
+    );
+
+    // Create Receiver PDA account and send a document paid by the funder
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(
+                &payer.pubkey(),
+                &funder.pubkey(),
+                LAMPORTS_PER_SOL,
+            ),
+            create_receiver_account(
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+            ),
+            send_funded_document(
+                &funder.pubkey(),
+                &payer.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                0,
+                get_documents_dummy_data()[0].clone(),
+                None,
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &funder],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let document_pda_account = banks_client
+        .get_account(document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_pda_account not found");
+
+    let document = Document::try_from_slice(&document_pda_account.data).unwrap();
+
+    assert_eq!(document.id, document_id);
+    assert_eq!(document.funder, funder.pubkey());
+
+    // Document can be looked up by its ID
+    let document_locator_pda_account = banks_client
+        .get_account(document_locator_pda_address)
+        .await
+        .expect("get_account")
+        .expect("document_locator_pda_account not found");
+
+    assert_eq!(
+        DocumentLocator::try_from_slice(&document_locator_pda_account.data).unwrap(),
+        DocumentLocator {
+            receiver: receiver_wallet.pubkey(),
+            document_index: 0,
+        },
+    );
+
+    // Outbox of the sender references the document
+    let sent_document_pda_address = SentDocument::find_pda_address(
+        document.sent_document_index,
+        &payer.pubkey(),
+        &id(),
+    );
+    let sent_document_pda_account = banks_client
+        .get_account(sent_document_pda_address)
+        .await
+        .expect("get_account")
+        .expect("sent_document_pda_account not found");
+
+    let document_lamports = document_pda_account.lamports
+        + document_locator_pda_account.lamports
+        + sent_document_pda_account.lamports;
+
+    // Unopened document can not be closed
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_document(
+            &receiver_wallet.pubkey(),
+            &receiver_wallet.pubkey(),
+            0,
+            &document,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::NotOpened as u32),
+        ),
+    );
+
+    // Sender can not close an unopened document it did not recall
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_document(
+            &payer.pubkey(),
+            &receiver_wallet.pubkey(),
+            0,
+            &document,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::DocumentNotRevokedOrExpired as u32),
+        ),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            mark_document_opened(
+                &receiver_wallet.pubkey(),
+                0,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Sender can not close the document the receiver opened
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_document(
+            &payer.pubkey(),
+            &receiver_wallet.pubkey(),
+            0,
+            &document,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::AlreadyOpened as u32),
+        ),
+    );
+
+    // Rent can only be returned to the funder
+    let mut instruction = close_document(
+        &receiver_wallet.pubkey(),
+        &receiver_wallet.pubkey(),
+        0,
+        &document,
+        None,
+    );
+    instruction.accounts[4].pubkey = receiver_wallet.pubkey();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(DocumentsError::NotFunder as u32),
+        ),
+    );
+
+    let funder_balance = banks_client
+        .get_balance(funder.pubkey())
+        .await
+        .unwrap();
+
+    // Receiver closes the opened document
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            close_document(
+                &receiver_wallet.pubkey(),
+                &receiver_wallet.pubkey(),
+                0,
+                &document,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &receiver_wallet],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Document, document locator and sent document PDA accounts do not exist anymore
+    assert_eq!(
+        banks_client
+            .get_account(document_pda_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+
+    assert_eq!(
+        banks_client
+            .get_account(document_locator_pda_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+
+    assert_eq!(
+        banks_client
+            .get_account(sent_document_pda_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+
+    assert_eq!(
+        banks_client
+            .get_balance(funder.pubkey())
+            .await
+            .unwrap(),
+        funder_balance + document_lamports,
+    );
+
+    // Documents counter of the receiver is not decremented
+    let receiver_pda_account = banks_client
+        .get_account(receiver_pda_address)
+        .await
+        .expect("get_account")
+        .expect("receiver_pda_account not found");
+
+    assert_eq!(
+        Receiver::try_from_slice(&receiver_pda_account.data)
+            .unwrap()
+            .documents_counter,
+        1,
+    );
+}
+
+fn get_documents_dummy_data() -> Vec<DocumentPayload> {
+    let documents_data = vec![
+        DocumentPayload {
+            version: DocumentPayload::VERSION,
+            uri: String::from("https://emn178.github.io/online-tools/sha256.html"),
+            hash_algorithm: HashAlgorithm::Sha256,
+            digest: vec![
+                0xe2, 0xc1, 0xfc, 0xbd, 0x5b, 0x4b, 0xef, 0xac, 0xb2, 0xeb, 0xdc, 0x5a, 0x7b, 0x6e, 0x6d, 0xa8,
+                0x6a, 0xd5, 0xb2, 0xa1, 0xeb, 0xb5, 0x03, 0x71, 0xa5, 0x46, 0xd1, 0x97, 0x46, 0x71, 0x65, 0xc9,
+            ],
+            mime_type: String::from("text/html"),
+            size: 1024,
+            encryption_scheme: EncryptionScheme::None,
+            expires_at: None,
+        },
+        DocumentPayload {
+            version: DocumentPayload::VERSION,
+            uri: String::from("https://emn178.github.io/online-tools/sha512.html"),
+            hash_algorithm: HashAlgorithm::Sha512,
+            digest: vec![0x28; 64],
+            mime_type: String::from("application/pdf"),
+            size: 2048,
+            encryption_scheme: EncryptionScheme::X25519XSalsa20Poly1305,
+            expires_at: None,
+        },
     ];
 
     documents_data
@@ -3,9 +3,6 @@ use {
         id,
         process_instruction,
     },
-    solana_program::{
-        pubkey::Pubkey,
-    },
     solana_program_test::*,
 };
 
@@ -17,7 +14,7 @@ pub fn program_test() -> ProgramTest {
     );
 
     // Dial down the BPF compute budget to detect if the program gets bloated in the future
-    program_test.set_bpf_compute_max_units(50_000);
+    program_test.set_compute_max_units(50_000);
 
     program_test
 }